@@ -0,0 +1,255 @@
+//! Playfield model
+//!
+//! The rules are written against a const-generic board so the same code runs
+//! the 5x5 LED grid on the micro:bit v2 and a standard 10x20 playfield. The
+//! board only records what occupies each cell; see [`crate::render`] for how
+//! that becomes pixels.
+use core::ops::{Index, IndexMut};
+
+use crate::tetrominos::PieceKind;
+
+/// State of a single cell on the board
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Cell {
+    /// Nothing occupies the cell
+    #[default]
+    Empty,
+    /// Part of a piece that has locked in place
+    Locked(PieceKind),
+    /// Part of the currently falling piece
+    Active,
+}
+
+impl Cell {
+    /// Check if the cell holds a locked block
+    ///
+    /// # Returns
+    /// * True if the cell is locked, false otherwise
+    pub const fn is_locked(self) -> bool {
+        matches!(self, Cell::Locked(_))
+    }
+}
+
+/// Playfield of `W` columns by `H` rows. Indexed as row and then column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Board<const W: usize, const H: usize> {
+    /// Cells of the board
+    cells: [[Cell; W]; H],
+}
+
+impl<const W: usize, const H: usize> Default for Board<W, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const W: usize, const H: usize> Board<W, H> {
+    /// Create a new empty board
+    pub const fn new() -> Self {
+        Self {
+            cells: [[Cell::Empty; W]; H],
+        }
+    }
+    /// Iterate over the rows of the board from top to bottom
+    ///
+    /// # Returns
+    /// * An iterator over each row of cells
+    pub fn rows(&self) -> impl Iterator<Item = &[Cell; W]> {
+        self.cells.iter()
+    }
+    /// Iterate mutably over the rows of the board from top to bottom
+    ///
+    /// # Returns
+    /// * An iterator over each row of cells
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [Cell; W]> {
+        self.cells.iter_mut()
+    }
+    /// Empty every row that is full of locked blocks
+    ///
+    /// # Returns
+    /// * Which rows were cleared, indexed from the top
+    pub fn clear_full_rows(&mut self) -> [bool; H] {
+        let mut cleared = [false; H];
+        for (row, was_cleared) in self.cells.iter_mut().zip(&mut cleared) {
+            if row.iter().all(|cell| cell.is_locked()) {
+                *row = [Cell::Empty; W];
+                *was_cleared = true;
+            }
+        }
+        cleared
+    }
+    /// Shift whole rows down over the cleared rows below them
+    ///
+    /// Naive gravity, as in standard Tetris. Blocks keep their shape, so
+    /// overhangs stay in the air.
+    ///
+    /// # Arguments
+    /// * `cleared` - Which rows were cleared, from [`Board::clear_full_rows`]
+    pub fn collapse_rows(&mut self, cleared: &[bool; H]) {
+        let mut target = H;
+        for row in (0..H).rev() {
+            if cleared[row] {
+                continue;
+            }
+            target -= 1;
+            self.cells[target] = self.cells[row];
+        }
+        for row in &mut self.cells[..target] {
+            *row = [Cell::Empty; W];
+        }
+    }
+    /// Drop every locked block down its column until it lands on something
+    ///
+    /// Cascade gravity. Each block falls on its own, so overhangs collapse.
+    ///
+    /// # Returns
+    /// * True if any block moved, false otherwise
+    pub fn drop_cells(&mut self) -> bool {
+        let mut moved = false;
+        for row in (0..H).rev() {
+            for col in 0..W {
+                if self.cells[row][col].is_locked() {
+                    let mut curr_row = row;
+                    while curr_row + 1 != H && self.cells[curr_row + 1][col] == Cell::Empty {
+                        self.cells[curr_row + 1][col] = self.cells[curr_row][col];
+                        self.cells[curr_row][col] = Cell::Empty;
+                        curr_row += 1;
+                        moved = true;
+                    }
+                }
+            }
+        }
+        moved
+    }
+    /// Drop each connected group of locked blocks as one unit until they land
+    ///
+    /// Sticky gravity. Blocks that touch side by side or top to bottom fall
+    /// together, and stick to whatever they land on.
+    ///
+    /// # Returns
+    /// * True if any block moved, false otherwise
+    pub fn drop_chunks(&mut self) -> bool {
+        let mut moved = false;
+        loop {
+            let labels = self.chunk_labels();
+            let falling = (1..=W * H).find(|&label| self.chunk_can_fall(&labels, label));
+            let Some(label) = falling else {
+                return moved;
+            };
+            let mut labels = labels;
+            while self.chunk_can_fall(&labels, label) {
+                // Move from the bottom up so no block overwrites another
+                for row in (0..H - 1).rev() {
+                    #[allow(clippy::needless_range_loop)]
+                    for col in 0..W {
+                        if labels[row][col] == label {
+                            self.cells[row + 1][col] = self.cells[row][col];
+                            self.cells[row][col] = Cell::Empty;
+                            labels[row + 1][col] = label;
+                            labels[row][col] = 0;
+                        }
+                    }
+                }
+            }
+            moved = true;
+        }
+    }
+    /// Label each connected group of locked blocks
+    ///
+    /// # Returns
+    /// * The label of the group each cell belongs to, 0 for cells without a block
+    fn chunk_labels(&self) -> [[usize; W]; H] {
+        // Start every block with its own label, then spread the smallest label
+        // through each group until nothing changes
+        let mut labels = [[0; W]; H];
+        for (row, label_row) in labels.iter_mut().enumerate() {
+            for (col, label) in label_row.iter_mut().enumerate() {
+                if self.cells[row][col].is_locked() {
+                    *label = row * W + col + 1;
+                }
+            }
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for row in 0..H {
+                for col in 0..W {
+                    if labels[row][col] == 0 {
+                        continue;
+                    }
+                    let neighbours = [
+                        row.checked_sub(1).map(|r| labels[r][col]),
+                        labels.get(row + 1).map(|r| r[col]),
+                        col.checked_sub(1).map(|c| labels[row][c]),
+                        labels[row].get(col + 1).copied(),
+                    ];
+                    for label in neighbours.into_iter().flatten() {
+                        if label != 0 && label < labels[row][col] {
+                            labels[row][col] = label;
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+        labels
+    }
+    /// Check if a group of blocks has room to fall one row
+    ///
+    /// # Arguments
+    /// * `labels` - Group labels from [`Board::chunk_labels`]
+    /// * `label` - The group to check
+    ///
+    /// # Returns
+    /// * True if the group exists and nothing outside it is below any of its blocks
+    fn chunk_can_fall(&self, labels: &[[usize; W]; H], label: usize) -> bool {
+        let mut found = false;
+        for row in 0..H {
+            #[allow(clippy::needless_range_loop)]
+            for col in 0..W {
+                if labels[row][col] != label {
+                    continue;
+                }
+                found = true;
+                let below_free = row + 1 < H
+                    && (labels[row + 1][col] == label || !self.cells[row + 1][col].is_locked());
+                if !below_free {
+                    return false;
+                }
+            }
+        }
+        found
+    }
+    /// Build a board from text rows lined up with the bottom of the board
+    ///
+    /// `#` is a locked block and anything else is an empty cell.
+    ///
+    /// # Arguments
+    /// * `rows` - The bottom rows of the board, from top to bottom
+    #[cfg(test)]
+    pub(crate) fn from_rows(rows: &[&str]) -> Self {
+        let mut board = Self::new();
+        for (row, line) in board.cells[H - rows.len()..].iter_mut().zip(rows) {
+            for (cell, c) in row.iter_mut().zip(line.chars()) {
+                if c == '#' {
+                    *cell = Cell::Locked(PieceKind::O);
+                }
+            }
+        }
+        board
+    }
+}
+
+impl<const W: usize, const H: usize> Index<usize> for Board<W, H> {
+    type Output = [Cell; W];
+
+    fn index(&self, row: usize) -> &Self::Output {
+        &self.cells[row]
+    }
+}
+
+impl<const W: usize, const H: usize> IndexMut<usize> for Board<W, H> {
+    fn index_mut(&mut self, row: usize) -> &mut Self::Output {
+        &mut self.cells[row]
+    }
+}
@@ -0,0 +1,284 @@
+//! Control schemes that turn player input into game actions
+//!
+//! The game loop asks a [`Controller`] for the actions due at each input tick,
+//! without knowing whether they came from the buttons, the logo, or tilting
+//! the board. Each cargo feature of the firmware picks the controllers it has
+//! hardware for, and two controllers can be played at once as a pair.
+use crate::edges::EdgeQueue;
+use crate::game::Action;
+use crate::input::{AutoRepeat, AutoShift, ButtonEvent, Controls, Input};
+
+/// Most actions one input tick can ask for
+pub const MAX_ACTIONS: usize = 8;
+
+/// Actions collected during one input tick, in the order they happened
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Actions {
+    /// Slots for the actions, the first `len` are in use
+    items: [Action; MAX_ACTIONS],
+    /// Number of actions collected
+    len: usize,
+}
+
+impl Default for Actions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Actions {
+    /// Create an empty list of actions
+    pub const fn new() -> Self {
+        Self {
+            items: [Action::SoftDrop; MAX_ACTIONS],
+            len: 0,
+        }
+    }
+    /// Add an action to the end
+    ///
+    /// # Arguments
+    /// * `action` - The action to add
+    ///
+    /// # Returns
+    /// * True if the action was added, false if the list was full
+    pub fn push(&mut self, action: Action) -> bool {
+        if self.len == MAX_ACTIONS {
+            return false;
+        }
+        self.items[self.len] = action;
+        self.len += 1;
+        true
+    }
+    /// Add the same action a number of times
+    ///
+    /// # Arguments
+    /// * `action` - The action to add
+    /// * `count` - How many times to add it, stopping early if the list fills
+    pub fn repeat(&mut self, action: Action, count: u32) {
+        for _ in 0..count {
+            if !self.push(action) {
+                break;
+            }
+        }
+    }
+    /// Check if no actions were collected
+    ///
+    /// # Returns
+    /// * True if the list is empty
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// The actions collected so far
+    ///
+    /// # Returns
+    /// * The actions in the order they were added
+    pub fn as_slice(&self) -> &[Action] {
+        &self.items[..self.len]
+    }
+}
+
+/// Something the player controls the game with
+pub trait Controller {
+    /// Collect the actions that are due
+    ///
+    /// # Arguments
+    /// * `now` - The current time in milliseconds
+    /// * `actions` - Where to add the actions
+    fn poll(&mut self, now: u32, actions: &mut Actions);
+}
+
+impl<T: Controller + ?Sized> Controller for &mut T {
+    fn poll(&mut self, now: u32, actions: &mut Actions) {
+        (**self).poll(now, actions)
+    }
+}
+
+/// Two controllers played at once, the first one's actions coming first
+impl<S: Controller, T: Controller> Controller for (S, T) {
+    fn poll(&mut self, now: u32, actions: &mut Actions) {
+        self.0.poll(now, actions);
+        self.1.poll(now, actions);
+    }
+}
+
+/// The classic scheme of the A and B buttons and the logo, fed by edges
+///
+/// A moves left and B moves right, repeating while held on their own, and
+/// touching the logo rotates clockwise. Pressing A and B together hard drops,
+/// holding A while touching the logo rotates counter-clockwise, and holding B
+/// while touching the logo holds the piece. Inputs missing from the queue are
+/// never pressed, so the same scheme works with only the logo.
+pub struct ButtonController<Q> {
+    /// Edges of the inputs, oldest first
+    edges: Q,
+    /// Debounced level of each input
+    controls: Controls,
+    /// How held movement buttons repeat
+    repeat: AutoRepeat,
+    /// Auto-repeat of the A button
+    shift_left: AutoShift,
+    /// Auto-repeat of the B button
+    shift_right: AutoShift,
+}
+
+impl<Q: EdgeQueue> ButtonController<Q> {
+    /// Create a controller with every input released
+    ///
+    /// # Arguments
+    /// * `edges` - Where the edges of the inputs arrive
+    /// * `settle_ms` - Milliseconds a new level has to hold before it counts
+    /// * `repeat` - How held movement buttons repeat
+    pub const fn new(edges: Q, settle_ms: u32, repeat: AutoRepeat) -> Self {
+        Self {
+            edges,
+            controls: Controls::new(settle_ms),
+            repeat,
+            shift_left: AutoShift::new(),
+            shift_right: AutoShift::new(),
+        }
+    }
+}
+
+impl<Q: EdgeQueue> Controller for ButtonController<Q> {
+    fn poll(&mut self, now: u32, actions: &mut Actions) {
+        // Presses of each input in the order of [`Input::ALL`], and moves due
+        // to the left and to the right
+        let mut presses = [false; 3];
+        let mut moves = (0, 0);
+        let Self {
+            edges,
+            controls,
+            repeat,
+            shift_left,
+            shift_right,
+        } = self;
+        controls.poll(edges, now, |input, event| {
+            presses[input as usize] |= event == ButtonEvent::Pressed;
+            match input {
+                Input::A => moves.0 += shift_left.update(Some(event), repeat),
+                Input::B => moves.1 += shift_right.update(Some(event), repeat),
+                Input::Logo => {}
+            }
+        });
+        let [press_a, press_b, press_logo] = presses;
+        let a = self.controls.is_pressed(Input::A);
+        let b = self.controls.is_pressed(Input::B);
+        let logo = self.controls.is_pressed(Input::Logo);
+        if (press_a && b) || (press_b && a) {
+            actions.push(Action::HardDrop);
+        } else if (press_a && logo) || (press_logo && a) {
+            actions.push(Action::RotateCounterClockwise);
+        } else if (press_b && logo) || (press_logo && b) {
+            actions.push(Action::Hold);
+        } else {
+            // Move once on a press, then repeat while held on its own
+            if !b && !logo {
+                actions.repeat(Action::MoveLeft, moves.0);
+            }
+            if !a && !logo {
+                actions.repeat(Action::MoveRight, moves.1);
+            }
+            if press_logo {
+                actions.push(Action::RotateClockwise);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edges::{Edge, EdgeRing};
+
+    /// Edge of an input
+    fn edge(at: u32, input: Input, pressed: bool) -> Edge {
+        Edge { at, input, pressed }
+    }
+
+    /// Poll a controller once
+    fn poll<C: Controller>(controller: &mut C, now: u32) -> Actions {
+        let mut actions = Actions::new();
+        controller.poll(now, &mut actions);
+        actions
+    }
+
+    #[test]
+    fn actions_fill_up_and_stop() {
+        let mut actions = Actions::new();
+        assert!(actions.is_empty());
+        actions.repeat(Action::MoveLeft, 3);
+        actions.repeat(Action::MoveRight, 10);
+        assert_eq!(actions.as_slice().len(), MAX_ACTIONS);
+        assert_eq!(
+            actions.as_slice()[..4],
+            [
+                Action::MoveLeft,
+                Action::MoveLeft,
+                Action::MoveLeft,
+                Action::MoveRight
+            ]
+        );
+        assert!(!actions.push(Action::HardDrop));
+    }
+
+    #[test]
+    fn buttons_move_and_repeat() {
+        let ring = EdgeRing::<8>::new();
+        let (mut producer, consumer) = ring.split().unwrap();
+        let repeat = AutoRepeat {
+            delay_ms: 100,
+            rate_ms: 50,
+        };
+        let mut buttons = ButtonController::new(consumer, 10, repeat);
+        producer.push(edge(0, Input::A, true));
+        assert!(poll(&mut buttons, 5).is_empty());
+        assert_eq!(poll(&mut buttons, 10).as_slice(), [Action::MoveLeft]);
+        assert!(poll(&mut buttons, 90).is_empty());
+        assert_eq!(
+            poll(&mut buttons, 160).as_slice(),
+            [Action::MoveLeft, Action::MoveLeft]
+        );
+        producer.push(edge(170, Input::A, false));
+        assert!(poll(&mut buttons, 200).is_empty());
+        // A tap of the logo between two polls still rotates
+        producer.push(edge(210, Input::Logo, true));
+        producer.push(edge(240, Input::Logo, false));
+        assert_eq!(
+            poll(&mut buttons, 250).as_slice(),
+            [Action::RotateClockwise]
+        );
+    }
+
+    #[test]
+    fn chords_act_once() {
+        let ring = EdgeRing::<8>::new();
+        let (mut producer, consumer) = ring.split().unwrap();
+        let mut buttons = ButtonController::new(consumer, 10, AutoRepeat::LED);
+        producer.push(edge(0, Input::A, true));
+        producer.push(edge(2, Input::B, true));
+        assert_eq!(poll(&mut buttons, 20).as_slice(), [Action::HardDrop]);
+        // Holding both does not move either way
+        assert!(poll(&mut buttons, 1000).is_empty());
+        producer.push(edge(1010, Input::A, false));
+        producer.push(edge(1020, Input::Logo, true));
+        assert_eq!(poll(&mut buttons, 1040).as_slice(), [Action::Hold]);
+    }
+
+    /// Controller that asks for the same action every time
+    struct Always(Action);
+
+    impl Controller for Always {
+        fn poll(&mut self, _now: u32, actions: &mut Actions) {
+            actions.push(self.0);
+        }
+    }
+
+    #[test]
+    fn paired_controllers_both_act() {
+        let mut pair = (Always(Action::RotateClockwise), Always(Action::SoftDrop));
+        assert_eq!(
+            poll(&mut pair, 0).as_slice(),
+            [Action::RotateClockwise, Action::SoftDrop]
+        );
+    }
+}
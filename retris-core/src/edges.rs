@@ -0,0 +1,321 @@
+//! Timestamped pin edges passed from an interrupt to the game loop
+//!
+//! The firmware's GPIOTE interrupt pushes an [`Edge`] each time a button or
+//! the logo changes level, and the game loop drains them in time order and
+//! feeds them to the debouncers in [`crate::input::Controls`]. Each input has
+//! its own ring buffer, lock-free with one producer and one consumer, so the
+//! interrupt never waits on the game loop.
+//!
+//! [`EdgeQueue`] is the one trait every input source implements: the
+//! firmware's buttons and logo, a pair of sources drained together in time
+//! order, and a [`Script`] of recorded pin traces, so the debouncing and
+//! controls run on the host exactly as they do on the board.
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::input::Input;
+use crate::schedule::reached;
+
+/// A change in level of one input
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Edge {
+    /// When the change happened, in milliseconds
+    pub at: u32,
+    /// The input that changed
+    pub input: Input,
+    /// The new level, true if pressed
+    pub pressed: bool,
+}
+
+impl Edge {
+    /// Placeholder for unused slots of a queue
+    const EMPTY: Edge = Edge {
+        at: 0,
+        input: Input::A,
+        pressed: false,
+    };
+}
+
+/// Edges waiting for the game loop, oldest first
+pub trait EdgeQueue {
+    /// Look at the oldest edge without taking it
+    ///
+    /// # Returns
+    /// * The oldest edge, or None if the queue is empty
+    fn peek(&self) -> Option<Edge>;
+    /// Take the oldest edge
+    ///
+    /// # Returns
+    /// * The oldest edge, or None if the queue is empty
+    fn pop(&mut self) -> Option<Edge>;
+    /// Take the oldest edge if it happened by a point in time
+    ///
+    /// Edges stamped after `now` stay queued, so a loop that reads the clock
+    /// and then drains never sees edges from its future.
+    ///
+    /// # Arguments
+    /// * `now` - The current time in milliseconds
+    ///
+    /// # Returns
+    /// * The oldest edge, or None if the queue is empty or it is too new
+    fn pop_until(&mut self, now: u32) -> Option<Edge> {
+        let edge = self.peek()?;
+        if !reached(now, edge.at) {
+            return None;
+        }
+        self.pop()
+    }
+}
+
+/// No inputs at all, for builds without buttons or logo
+impl EdgeQueue for () {
+    fn peek(&self) -> Option<Edge> {
+        None
+    }
+    fn pop(&mut self) -> Option<Edge> {
+        None
+    }
+}
+
+/// Two sources drained as one in time order, the first one's edge first when
+/// both happened at the same time
+impl<S: EdgeQueue, T: EdgeQueue> EdgeQueue for (S, T) {
+    fn peek(&self) -> Option<Edge> {
+        match (self.0.peek(), self.1.peek()) {
+            (Some(first), Some(second)) if !reached(second.at, first.at) => Some(second),
+            (Some(first), _) => Some(first),
+            (None, second) => second,
+        }
+    }
+    fn pop(&mut self) -> Option<Edge> {
+        match (self.0.peek(), self.1.peek()) {
+            (Some(first), Some(second)) if !reached(second.at, first.at) => self.1.pop(),
+            (Some(_), _) => self.0.pop(),
+            (None, _) => self.1.pop(),
+        }
+    }
+}
+
+/// Recorded pin traces played back as a source of edges
+///
+/// Each step is a time in milliseconds, an input, and the level it changes to,
+/// in time order. Steps are handed out like edges from the interrupt, so
+/// anything that drains an [`EdgeQueue`] can be tried on the host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Script<'a> {
+    /// Level changes in time order
+    steps: &'a [(u32, Input, bool)],
+    /// Number of steps taken so far
+    played: usize,
+}
+
+impl<'a> Script<'a> {
+    /// Create a script that starts at its first step
+    ///
+    /// # Arguments
+    /// * `steps` - Level changes in time order
+    pub const fn new(steps: &'a [(u32, Input, bool)]) -> Self {
+        Self { steps, played: 0 }
+    }
+}
+
+impl EdgeQueue for Script<'_> {
+    fn peek(&self) -> Option<Edge> {
+        let &(at, input, pressed) = self.steps.get(self.played)?;
+        Some(Edge { at, input, pressed })
+    }
+    fn pop(&mut self) -> Option<Edge> {
+        let edge = self.peek()?;
+        self.played += 1;
+        Some(edge)
+    }
+}
+
+/// Fixed size ring buffer of edges shared by one producer and one consumer
+///
+/// The buffer is split once into an [`EdgeProducer`] for the interrupt and an
+/// [`EdgeConsumer`] for the game loop, which is what makes it safe to share
+/// without a lock.
+pub struct EdgeRing<const N: usize> {
+    /// Slots of the ring, indexed by the counters modulo `N`
+    slots: UnsafeCell<[Edge; N]>,
+    /// Number of edges ever taken, only written by the consumer
+    head: AtomicUsize,
+    /// Number of edges ever added, only written by the producer
+    tail: AtomicUsize,
+    /// Set once the ring has been split
+    split: AtomicBool,
+}
+
+// SAFETY: slots are only written by the single producer before it publishes
+// them with `tail`, and only read by the single consumer before it frees them
+// with `head`, and `split` hands out at most one of each.
+unsafe impl<const N: usize> Sync for EdgeRing<N> {}
+
+impl<const N: usize> Default for EdgeRing<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> EdgeRing<N> {
+    /// Create an empty ring
+    pub const fn new() -> Self {
+        Self {
+            slots: UnsafeCell::new([Edge::EMPTY; N]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            split: AtomicBool::new(false),
+        }
+    }
+    /// Split the ring into its two ends
+    ///
+    /// # Returns
+    /// * The producer and consumer, or None if the ring was already split
+    pub fn split(&self) -> Option<(EdgeProducer<'_, N>, EdgeConsumer<'_, N>)> {
+        if self.split.swap(true, Ordering::AcqRel) {
+            return None;
+        }
+        Some((EdgeProducer { ring: self }, EdgeConsumer { ring: self }))
+    }
+}
+
+/// The end of an [`EdgeRing`] that adds edges
+pub struct EdgeProducer<'a, const N: usize> {
+    /// The shared ring
+    ring: &'a EdgeRing<N>,
+}
+
+impl<const N: usize> EdgeProducer<'_, N> {
+    /// Add an edge to the back of the ring
+    ///
+    /// # Arguments
+    /// * `edge` - The edge to add
+    ///
+    /// # Returns
+    /// * True if the edge was added, false if the ring was full
+    pub fn push(&mut self, edge: Edge) -> bool {
+        let tail = self.ring.tail.load(Ordering::Relaxed);
+        let head = self.ring.head.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) >= N {
+            return false;
+        }
+        // SAFETY: the slot at `tail` is free until `tail` is published below
+        unsafe { (*self.ring.slots.get())[tail % N] = edge };
+        self.ring
+            .tail
+            .store(tail.wrapping_add(1), Ordering::Release);
+        true
+    }
+}
+
+/// The end of an [`EdgeRing`] that takes edges
+pub struct EdgeConsumer<'a, const N: usize> {
+    /// The shared ring
+    ring: &'a EdgeRing<N>,
+}
+
+impl<const N: usize> EdgeQueue for EdgeConsumer<'_, N> {
+    fn peek(&self) -> Option<Edge> {
+        let head = self.ring.head.load(Ordering::Relaxed);
+        let tail = self.ring.tail.load(Ordering::Acquire);
+        if head == tail {
+            return None;
+        }
+        // SAFETY: the slot at `head` was published by the producer and is not
+        // reused until `head` moves past it
+        Some(unsafe { (*self.ring.slots.get())[head % N] })
+    }
+    fn pop(&mut self) -> Option<Edge> {
+        let edge = self.peek()?;
+        let head = self.ring.head.load(Ordering::Relaxed);
+        self.ring
+            .head
+            .store(head.wrapping_add(1), Ordering::Release);
+        Some(edge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Edge of the A button at a time
+    fn edge(at: u32, pressed: bool) -> Edge {
+        Edge {
+            at,
+            input: Input::A,
+            pressed,
+        }
+    }
+
+    #[test]
+    fn ring_is_first_in_first_out() {
+        let ring = EdgeRing::<4>::new();
+        let (mut producer, mut consumer) = ring.split().unwrap();
+        assert!(ring.split().is_none());
+        assert_eq!(consumer.pop(), None);
+        for at in 0..4 {
+            assert!(producer.push(edge(at, at % 2 == 0)));
+        }
+        assert!(!producer.push(edge(4, true)));
+        assert_eq!(consumer.pop(), Some(edge(0, true)));
+        assert!(producer.push(edge(4, true)));
+        for at in 1..5 {
+            assert_eq!(consumer.pop(), Some(edge(at, at % 2 == 0)));
+        }
+        assert_eq!(consumer.pop(), None);
+    }
+
+    #[test]
+    fn ring_keeps_working_as_counters_go_around() {
+        let ring = EdgeRing::<3>::new();
+        let (mut producer, mut consumer) = ring.split().unwrap();
+        for at in 0..100 {
+            assert!(producer.push(edge(at, true)));
+            assert!(producer.push(edge(at, false)));
+            assert_eq!(consumer.pop(), Some(edge(at, true)));
+            assert_eq!(consumer.pop(), Some(edge(at, false)));
+        }
+        assert_eq!(consumer.peek(), None);
+    }
+
+    #[test]
+    fn edges_from_the_future_wait() {
+        let ring = EdgeRing::<4>::new();
+        let (mut producer, mut consumer) = ring.split().unwrap();
+        producer.push(edge(10, true));
+        producer.push(edge(30, false));
+        assert_eq!(consumer.pop_until(5), None);
+        assert_eq!(consumer.pop_until(20), Some(edge(10, true)));
+        assert_eq!(consumer.pop_until(20), None);
+        assert_eq!(consumer.pop_until(40), Some(edge(30, false)));
+        // Still ordered across the clock wrapping around
+        producer.push(edge(u32::MAX - 5, true));
+        producer.push(edge(2, false));
+        assert_eq!(consumer.pop_until(u32::MAX), Some(edge(u32::MAX - 5, true)));
+        assert_eq!(consumer.pop_until(u32::MAX), None);
+        assert_eq!(consumer.pop_until(2), Some(edge(2, false)));
+    }
+
+    #[test]
+    fn paired_sources_drain_in_time_order() {
+        let logo = |at, pressed| Edge {
+            at,
+            input: Input::Logo,
+            pressed,
+        };
+        let buttons = [(0, Input::A, true), (20, Input::A, false)];
+        let touches = [(0, Input::Logo, true), (10, Input::Logo, false)];
+        let mut pair = (Script::new(&buttons), Script::new(&touches));
+        // The first source goes first on a tie
+        assert_eq!(pair.peek(), Some(edge(0, true)));
+        assert_eq!(pair.pop_until(30), Some(edge(0, true)));
+        assert_eq!(pair.pop_until(30), Some(logo(0, true)));
+        assert_eq!(pair.pop_until(5), None);
+        assert_eq!(pair.pop_until(30), Some(logo(10, false)));
+        assert_eq!(pair.pop_until(30), Some(edge(20, false)));
+        assert_eq!(pair.pop_until(30), None);
+        assert_eq!(().pop(), None);
+    }
+}
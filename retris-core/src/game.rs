@@ -0,0 +1,2170 @@
+//! Module for Game state and logic
+//!
+//! Helps processs frames, inputs, etc.
+use nanorand::Pcg64;
+
+use crate::board::{Board, Cell};
+use crate::queue::{PieceQueue, MAX_PREVIEW};
+use crate::randomizer::{PieceRandomizer, Randomizer};
+use crate::rules::{LineClear, LockReset, Rules};
+use crate::scoring::ScoreTable;
+use crate::tetrominos::{PieceKind, PieceSet, Rotation, Shape};
+
+/// Location of a piece, indexed by the top left corner of its bounding box
+///
+/// Signed so the empty edges of a bounding box can hang off the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PieceLocation {
+    /// Current row of the top left corner of the falling piece
+    pub row: isize,
+    /// Current column of the top left corner of the falling piece
+    pub col: isize,
+}
+
+/// The piece currently falling
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FallingPiece {
+    /// Kind of tetromino
+    pub kind: PieceKind,
+    /// Rotation state of the piece
+    pub rotation: Rotation,
+    /// Shape of the piece in its current rotation state
+    pub shape: Shape,
+    /// Location of the piece on the board
+    pub loc: PieceLocation,
+}
+
+impl FallingPiece {
+    /// Board cells covered by the piece, which may lie outside the board
+    ///
+    /// # Returns
+    /// * The row and column of every filled cell of the piece
+    pub fn cells(&self) -> impl Iterator<Item = (isize, isize)> {
+        let loc = self.loc;
+        self.shape
+            .filled()
+            .map(move |(row, col)| (loc.row + row as isize, loc.col + col as isize))
+    }
+}
+
+/// Something the player can do to the falling piece
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Move left one column
+    MoveLeft,
+    /// Move right one column
+    MoveRight,
+    /// Rotate 90 degrees clockwise
+    RotateClockwise,
+    /// Rotate 90 degrees counter-clockwise
+    RotateCounterClockwise,
+    /// Rotate 180 degrees
+    Rotate180,
+    /// Move down one row, locking if blocked
+    SoftDrop,
+    /// Drop to the landing row and lock
+    HardDrop,
+    /// Swap the falling piece with the held piece
+    Hold,
+}
+
+/// Reason the game ended
+///
+/// Game over reference: <https://tetris.wiki/Top_out>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameOverReason {
+    /// A new piece spawned overlapping locked blocks
+    BlockOut,
+    /// A piece locked entirely above the visible rows
+    LockOut,
+    /// A locked block was left in the top row after clearing
+    TopOut,
+}
+
+/// Kind of T-spin, found with the 3-corner rule when a T piece locks
+///
+/// T-spin reference: <https://tetris.wiki/T-Spin>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TSpin {
+    /// Three corners are filled but only one in front of the T's point
+    Mini,
+    /// Three corners are filled including both in front of the T's point
+    Full,
+}
+
+/// Everything that happened during a single [`GameState::step`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StepOutcome {
+    /// Number of full rows cleared
+    pub lines_cleared: u8,
+    /// The falling piece was added to the solid blocks
+    pub piece_locked: bool,
+    /// A new piece was spawned at the top of the board
+    pub piece_spawned: bool,
+    /// Set when the game is over, along with why
+    pub game_over: Option<GameOverReason>,
+    /// Rows the piece moved down by soft dropping
+    pub soft_drop: u8,
+    /// Rows the piece moved down by hard dropping
+    pub hard_drop: u8,
+    /// The falling piece was swapped into the hold slot
+    pub piece_held: bool,
+    /// Points scored for clears and drops
+    pub points: u32,
+    /// The locked piece was a T-spin
+    pub t_spin: Option<TSpin>,
+    /// The clear left the board without any blocks
+    pub all_clear: bool,
+    /// Set when the locked piece cleared rows, to how many locks in a row
+    /// cleared rows before it
+    pub combo: Option<u8>,
+    /// Set when the locked piece made a difficult clear, to how many difficult
+    /// clears came before it without an easier clear in between
+    pub back_to_back: Option<u8>,
+}
+
+impl StepOutcome {
+    /// Combine this outcome with one that happened right after it
+    ///
+    /// # Arguments
+    /// * `next` - The later outcome
+    ///
+    /// # Returns
+    /// * Everything that happened across both outcomes
+    pub fn then(self, next: StepOutcome) -> StepOutcome {
+        StepOutcome {
+            lines_cleared: self.lines_cleared + next.lines_cleared,
+            piece_locked: self.piece_locked || next.piece_locked,
+            piece_spawned: self.piece_spawned || next.piece_spawned,
+            game_over: self.game_over.or(next.game_over),
+            soft_drop: self.soft_drop + next.soft_drop,
+            hard_drop: self.hard_drop + next.hard_drop,
+            piece_held: self.piece_held || next.piece_held,
+            points: self.points + next.points,
+            t_spin: self.t_spin.or(next.t_spin),
+            all_clear: self.all_clear || next.all_clear,
+            combo: self.combo.or(next.combo),
+            back_to_back: self.back_to_back.or(next.back_to_back),
+        }
+    }
+    /// Check if the outcome is a difficult clear, which keeps a back-to-back
+    /// chain going
+    ///
+    /// # Arguments
+    /// * `scoring` - The score table of the rules, whose biggest clear is difficult
+    ///
+    /// # Returns
+    /// * True for the biggest clear in the table or a T-spin that clears rows
+    pub fn difficult(&self, scoring: &ScoreTable) -> bool {
+        self.lines_cleared > 0
+            && (self.t_spin.is_some() || self.lines_cleared as usize >= scoring.lines.len() - 1)
+    }
+}
+
+/// Game state struct for handling game logic on a `W` by `H` board
+pub struct GameState<const W: usize, const H: usize> {
+    /// Rules the game is played with
+    rules: Rules,
+    /// Locked blocks and the falling piece
+    board: Board<W, H>,
+    /// Current piece falling, if any
+    falling: Option<FallingPiece>,
+    /// Picks the pieces that fill the queue
+    randomizer: PieceRandomizer,
+    /// Pieces waiting to spawn after the falling piece
+    queue: PieceQueue,
+    /// Piece in the hold slot, if any
+    held: Option<PieceKind>,
+    /// Set once the falling piece has been held, cleared when a piece locks
+    hold_used: bool,
+    /// Milliseconds since the game started, moved forward by [`GameState::advance`]
+    clock: u32,
+    /// When the falling piece came to rest on the stack, if it is resting
+    lock_started: Option<u32>,
+    /// Lock delay resets used since the piece reached its lowest row
+    lock_resets: u8,
+    /// Lowest row the top of the falling piece has reached
+    lowest_row: isize,
+    /// Set when the last thing the falling piece did was rotate
+    rotated: bool,
+    /// Set when that rotation used the last SRS kick, which makes any T-spin full
+    far_kick: bool,
+    /// Locks in a row that cleared rows, less one, or None after a lock without a clear
+    combo: Option<u8>,
+    /// Difficult clears in a row, less one, or None after an easier clear
+    back_to_back: Option<u8>,
+    /// Points scored so far
+    score: u32,
+    /// Rows cleared so far
+    lines: u32,
+    /// Current level, which multiplies the points for clears
+    level: u32,
+    /// Set once the game is over, along with why
+    game_over: Option<GameOverReason>,
+}
+
+impl<const W: usize, const H: usize> Default for GameState<W, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const W: usize, const H: usize> GameState<W, H> {
+    /// Create a new GameState with the default rules for the 5x5 pixel display
+    pub fn new() -> Self {
+        Self::with_rules(Rules::default())
+    }
+    /// Create a new GameState
+    ///
+    /// # Arguments
+    /// * `rules` - The rules to play with
+    pub fn with_rules(rules: Rules) -> Self {
+        Self {
+            rules,
+            board: Board::new(),
+            falling: None,
+            randomizer: PieceRandomizer::new(rules.randomizer, rules.piece_set),
+            queue: PieceQueue::new(),
+            held: None,
+            hold_used: false,
+            clock: 0,
+            lock_started: None,
+            lock_resets: 0,
+            lowest_row: 0,
+            rotated: false,
+            far_kick: false,
+            combo: None,
+            back_to_back: None,
+            score: 0,
+            lines: 0,
+            level: rules.scoring.level(0),
+            game_over: None,
+        }
+    }
+    /// The board with its locked blocks and falling piece
+    ///
+    /// # Returns
+    /// * A borrowed board, for rendering with [`crate::render::render`]
+    pub fn board(&self) -> &Board<W, H> {
+        &self.board
+    }
+    /// The currently falling piece
+    ///
+    /// # Returns
+    /// * The falling piece, or None between a lock and the next spawn
+    pub fn falling_piece(&self) -> Option<&FallingPiece> {
+        self.falling.as_ref()
+    }
+    /// The upcoming pieces
+    ///
+    /// # Returns
+    /// * Up to `preview` pieces from the rules in the order they will spawn,
+    ///   empty until the first step
+    pub fn next_pieces(&self) -> &[PieceKind] {
+        self.queue.as_slice()
+    }
+    /// The rules this game is played by
+    ///
+    /// # Returns
+    /// * A reference to the game's rules
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+    /// The piece in the hold slot
+    ///
+    /// # Returns
+    /// * The kind of the held piece, or None if nothing has been held yet
+    pub fn held_piece(&self) -> Option<PieceKind> {
+        self.held
+    }
+    /// Points scored so far
+    ///
+    /// # Returns
+    /// * The score
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+    /// Rows cleared so far
+    ///
+    /// # Returns
+    /// * The total number of lines cleared
+    pub fn lines(&self) -> u32 {
+        self.lines
+    }
+    /// Current level
+    ///
+    /// # Returns
+    /// * The level, starting from 1
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+    /// Time until the falling piece should drop another row
+    ///
+    /// # Returns
+    /// * The gravity interval for the current level in milliseconds, read it
+    ///   each frame since it shortens as the level goes up
+    pub fn gravity_interval(&self) -> u16 {
+        self.rules.gravity.interval(self.level)
+    }
+    /// Why the game ended
+    ///
+    /// # Returns
+    /// * The reason the game is over, or None if it is still being played
+    pub fn game_over(&self) -> Option<GameOverReason> {
+        self.game_over
+    }
+    /// Check if a piece fits on the board without leaving it or overlapping solid blocks
+    ///
+    /// # Arguments
+    /// * `piece` - The piece at the location to check
+    ///
+    /// # Returns
+    /// * True if the piece fits, false otherwise
+    fn fits(&self, piece: &FallingPiece) -> bool {
+        piece.cells().all(|(row, col)| {
+            (0..H as isize).contains(&row)
+                && (0..W as isize).contains(&col)
+                && !self.board[row as usize][col as usize].is_locked()
+        })
+    }
+    /// Fill the board cells covered by the falling piece
+    ///
+    /// # Arguments
+    /// * `from` - The cell state to overwrite
+    /// * `to` - The cell state to write
+    fn paint_piece(&mut self, from: Cell, to: Cell) {
+        if let Some(piece) = self.falling {
+            for (row, col) in piece.cells() {
+                let cell = &mut self.board[row as usize][col as usize];
+                if *cell == from {
+                    *cell = to;
+                }
+            }
+        }
+    }
+    /// Place the piece on the board
+    fn place_piece(&mut self) {
+        self.paint_piece(Cell::Empty, Cell::Active);
+    }
+    /// Remove the piece from the board
+    fn erase_piece(&mut self) {
+        self.paint_piece(Cell::Active, Cell::Empty);
+    }
+    /// Spawn a piece at the top middle of the board
+    ///
+    /// # Arguments
+    /// * `kind` - The kind of piece to spawn
+    ///
+    /// # Returns
+    /// * A spawned piece outcome, or a block out if locked blocks are in the way
+    fn spawn_piece(&mut self, kind: PieceKind) -> StepOutcome {
+        let shape = self.rules.piece_set.shape(kind);
+        let col = self.rules.piece_set.spawn_col(W, shape);
+        let piece = FallingPiece {
+            kind,
+            rotation: Rotation::Spawn,
+            shape,
+            loc: PieceLocation {
+                row: 0,
+                col: col as isize,
+            },
+        };
+        if !self.fits(&piece) {
+            return self.end_game(GameOverReason::BlockOut);
+        }
+        self.falling = Some(piece);
+        self.place_piece();
+        self.lock_started = self.grounded().then_some(self.clock);
+        self.lock_resets = 0;
+        self.lowest_row = 0;
+        self.rotated = false;
+        StepOutcome {
+            piece_spawned: true,
+            ..StepOutcome::default()
+        }
+    }
+    /// End the game
+    ///
+    /// # Arguments
+    /// * `reason` - Why the game is over
+    ///
+    /// # Returns
+    /// * An outcome with the reason the game is over
+    fn end_game(&mut self, reason: GameOverReason) -> StepOutcome {
+        self.game_over = Some(reason);
+        StepOutcome {
+            game_over: self.game_over,
+            ..StepOutcome::default()
+        }
+    }
+    /// Move the falling piece to a new state if it fits there
+    ///
+    /// # Arguments
+    /// * `update` - Function that moves or rotates a copy of the piece
+    ///
+    /// # Returns
+    /// * True if the piece was moved, false if it was blocked or there is no piece
+    fn try_move(&mut self, update: impl FnOnce(&mut FallingPiece)) -> bool {
+        let Some(mut moved) = self.falling else {
+            return false;
+        };
+        if self.game_over.is_some() {
+            return false;
+        }
+        update(&mut moved);
+        self.erase_piece();
+        let fits = self.fits(&moved);
+        if fits {
+            self.falling = Some(moved);
+        }
+        self.place_piece();
+        fits
+    }
+    /// Move the currently falling piece left one column
+    pub fn move_left(&mut self) {
+        if self.try_move(|piece| piece.loc.col -= 1) {
+            self.rotated = false;
+            self.shifted();
+        }
+    }
+    /// Move the currently falling piece right one column
+    pub fn move_right(&mut self) {
+        if self.try_move(|piece| piece.loc.col += 1) {
+            self.rotated = false;
+            self.shifted();
+        }
+    }
+    /// Rotate the currently falling piece 90 degrees clockwise
+    pub fn rotate_piece(&mut self) {
+        if let Some(piece) = self.falling {
+            if self.try_rotate(piece.rotation.clockwise(), piece.shape.rotate_clockwise()) {
+                self.shifted();
+            }
+        }
+    }
+    /// Rotate the currently falling piece 90 degrees counter-clockwise
+    pub fn rotate_counter_clockwise(&mut self) {
+        if let Some(piece) = self.falling {
+            if self.try_rotate(
+                piece.rotation.counter_clockwise(),
+                piece.shape.rotate_counter_clockwise(),
+            ) {
+                self.shifted();
+            }
+        }
+    }
+    /// Rotate the currently falling piece 180 degrees
+    pub fn rotate_180(&mut self) {
+        if let Some(piece) = self.falling {
+            if self.try_rotate(piece.rotation.flip(), piece.shape.rotate_180()) {
+                self.shifted();
+            }
+        }
+    }
+    /// Move the game clock forward
+    ///
+    /// The lock delay is measured with this clock, so call it with the time
+    /// that passed before each step.
+    ///
+    /// # Arguments
+    /// * `elapsed_ms` - Milliseconds since the clock was last moved forward
+    pub fn advance(&mut self, elapsed_ms: u32) {
+        self.clock = self.clock.wrapping_add(elapsed_ms);
+    }
+    /// Time left before the resting piece locks
+    ///
+    /// # Returns
+    /// * Milliseconds until the lock delay runs out, or None if the falling
+    ///   piece is not resting on anything
+    pub fn lock_remaining(&self) -> Option<u32> {
+        let start = self.lock_started.filter(|_| self.grounded())?;
+        let elapsed = self.clock.wrapping_sub(start);
+        Some((self.rules.lock_delay.ms as u32).saturating_sub(elapsed))
+    }
+    /// Lock the falling piece if it is resting and its lock delay has run out
+    ///
+    /// Lets the firmware lock on time instead of waiting for the next gravity
+    /// step.
+    ///
+    /// # Returns
+    /// * What happened, with `piece_locked` set if the piece locked
+    pub fn lock_if_due(&mut self) -> StepOutcome {
+        if self.game_over.is_some() || self.lock_remaining() != Some(0) {
+            return StepOutcome::default();
+        }
+        self.lock_when_due()
+    }
+    /// Check if the falling piece is resting on the stack or the floor
+    ///
+    /// # Returns
+    /// * True if the piece cannot move down, false otherwise or if there is no piece
+    fn grounded(&self) -> bool {
+        self.falling.is_some_and(|mut piece| {
+            piece.loc.row += 1;
+            !self.fits(&piece)
+        })
+    }
+    /// Use up a lock delay reset, restarting the delay if the piece is resting
+    fn reset_lock(&mut self) {
+        if self.lock_started.is_none() || self.lock_resets >= self.rules.lock_delay.max_resets {
+            return;
+        }
+        self.lock_resets += 1;
+        self.lock_started = self.grounded().then_some(self.clock);
+    }
+    /// Reset the lock delay after the piece moved or rotated in place
+    fn shifted(&mut self) {
+        if self.rules.lock_delay.reset == LockReset::Move {
+            self.reset_lock();
+        }
+    }
+    /// Reset the lock delay after the piece fell a row
+    fn fell(&mut self) {
+        let Some(piece) = self.falling else {
+            return;
+        };
+        self.rotated = false;
+        if piece.loc.row > self.lowest_row {
+            // A new lowest row earns back every reset
+            self.lowest_row = piece.loc.row;
+            self.lock_resets = 0;
+            self.lock_started = self.grounded().then_some(self.clock);
+        } else {
+            self.reset_lock();
+        }
+    }
+    /// Move the currently falling piece down one row
+    ///
+    /// A piece that cannot move down goes through the lock delay like a
+    /// gravity step, so it can still slide before it locks. The caller keeps
+    /// the gravity timer, and restarts it when `soft_drop` is above 0.
+    ///
+    /// # Returns
+    /// * What happened, with the rows dropped in `soft_drop`
+    pub fn soft_drop(&mut self) -> StepOutcome {
+        if self.falling.is_none() || self.game_over.is_some() {
+            return StepOutcome::default();
+        }
+        if self.try_move(|piece| piece.loc.row += 1) {
+            self.fell();
+            return self.award(StepOutcome {
+                soft_drop: 1,
+                ..StepOutcome::default()
+            });
+        }
+        self.lock_when_due()
+    }
+    /// Drop the currently falling piece to its landing row and lock it
+    ///
+    /// # Returns
+    /// * What happened, with the rows dropped in `hard_drop`
+    pub fn hard_drop(&mut self) -> StepOutcome {
+        let Some(landing) = self.landing_loc() else {
+            return StepOutcome::default();
+        };
+        let mut distance = 0;
+        if let Some(piece) = self.falling {
+            distance = (landing.row - piece.loc.row) as u8;
+            if distance > 0 {
+                self.rotated = false;
+                self.try_move(|piece| piece.loc = landing);
+            }
+        }
+        let outcome = StepOutcome {
+            hard_drop: distance,
+            ..self.lock_piece()
+        };
+        self.award(outcome)
+    }
+    /// Swap the falling piece into the hold slot
+    ///
+    /// The held piece comes back out at the top of the board. If the slot was
+    /// empty, the next piece in the queue comes out instead, or spawns on the
+    /// following step when there is no queue. Only one hold is allowed each time
+    /// a piece locks. A piece that comes out onto locked blocks is a block out.
+    ///
+    /// # Returns
+    /// * What happened, with `piece_held` set if the piece was swapped
+    pub fn hold(&mut self) -> StepOutcome {
+        let Some(piece) = self.falling else {
+            return StepOutcome::default();
+        };
+        if self.hold_used || self.game_over.is_some() {
+            return StepOutcome::default();
+        }
+        self.erase_piece();
+        self.falling = None;
+        let mut outcome = StepOutcome::default();
+        if let Some(kind) = self.held.replace(piece.kind).or_else(|| self.queue.pop()) {
+            outcome = self.spawn_piece(kind);
+        }
+        self.hold_used = true;
+        StepOutcome {
+            piece_held: true,
+            ..outcome
+        }
+    }
+    /// The falling piece moved down to where a hard drop would lock it
+    ///
+    /// # Returns
+    /// * The ghost piece, or None if there is no piece falling
+    pub fn ghost_piece(&self) -> Option<FallingPiece> {
+        if self.game_over.is_some() {
+            return None;
+        }
+        let mut landing = *self.falling.as_ref()?;
+        loop {
+            let mut below = landing;
+            below.loc.row += 1;
+            if !self.fits(&below) {
+                return Some(landing);
+            }
+            landing = below;
+        }
+    }
+    /// Row the falling piece would lock at if it were hard dropped
+    ///
+    /// # Returns
+    /// * The row of the top of the piece's bounding box, or None if there is no
+    ///   piece falling
+    pub fn landing_row(&self) -> Option<isize> {
+        self.ghost_piece().map(|ghost| ghost.loc.row)
+    }
+    /// Location the falling piece would lock at if it were hard dropped
+    ///
+    /// # Returns
+    /// * The landing location, or None if there is no piece falling
+    fn landing_loc(&self) -> Option<PieceLocation> {
+        self.ghost_piece().map(|ghost| ghost.loc)
+    }
+    /// Apply a player action to the falling piece
+    ///
+    /// # Arguments
+    /// * `action` - The action to apply
+    ///
+    /// # Returns
+    /// * What happened, only drops can lock the piece and only holds swap it
+    pub fn apply(&mut self, action: Action) -> StepOutcome {
+        match action {
+            Action::MoveLeft => self.move_left(),
+            Action::MoveRight => self.move_right(),
+            Action::RotateClockwise => self.rotate_piece(),
+            Action::RotateCounterClockwise => self.rotate_counter_clockwise(),
+            Action::Rotate180 => self.rotate_180(),
+            Action::SoftDrop => return self.soft_drop(),
+            Action::HardDrop => return self.hard_drop(),
+            Action::Hold => return self.hold(),
+        }
+        StepOutcome::default()
+    }
+    /// Rotate the falling piece, trying each kick of the piece set in order
+    ///
+    /// # Arguments
+    /// * `rotation` - The rotation state to turn to
+    /// * `shape` - The shape of the piece in that rotation state
+    ///
+    /// # Returns
+    /// * True if the piece rotated, false if every kick was blocked
+    fn try_rotate(&mut self, rotation: Rotation, shape: Shape) -> bool {
+        let Some(piece) = self.falling else {
+            return false;
+        };
+        let kicks = self
+            .rules
+            .piece_set
+            .kicks(piece.kind, piece.rotation, rotation);
+        let kick = kicks.iter().position(|&(right, up)| {
+            self.try_move(|piece| {
+                piece.rotation = rotation;
+                piece.shape = shape;
+                piece.loc.col += right;
+                piece.loc.row -= up;
+            })
+        });
+        let Some(kick) = kick else {
+            return false;
+        };
+        self.rotated = true;
+        // The fifth kick of a quarter turn is the one that lets a T fit into
+        // a T-spin triple slot
+        self.far_kick = self.rules.piece_set == PieceSet::Srs && kicks.len() == 5 && kick == 4;
+        true
+    }
+    /// Check if a cell is a wall, the floor, or a locked block
+    ///
+    /// # Arguments
+    /// * `row` - The row of the cell, may be off the board
+    /// * `col` - The column of the cell, may be off the board
+    ///
+    /// # Returns
+    /// * True if a piece could not move into the cell
+    fn occupied(&self, row: isize, col: isize) -> bool {
+        !(0..H as isize).contains(&row)
+            || !(0..W as isize).contains(&col)
+            || self.board[row as usize][col as usize].is_locked()
+    }
+    /// Check if the falling piece is a T-spin using the 3-corner rule
+    ///
+    /// A T piece of the SRS set whose last move was a rotation is a T-spin if
+    /// at least three corners of its 3x3 box are occupied. It is a full T-spin
+    /// when both corners in front of its point are occupied, or when it got
+    /// there with the last kick, and a mini T-spin otherwise.
+    ///
+    /// # Returns
+    /// * The kind of T-spin, or None if it is not one
+    fn t_spin(&self) -> Option<TSpin> {
+        let piece = self.falling?;
+        if piece.kind != PieceKind::T || self.rules.piece_set != PieceSet::Srs || !self.rotated {
+            return None;
+        }
+        let corner =
+            |(row, col): (isize, isize)| self.occupied(piece.loc.row + row, piece.loc.col + col);
+        // Corners in front of the point of the T, then the two behind it
+        let (front, back) = match piece.rotation {
+            Rotation::Spawn => ([(0, 0), (0, 2)], [(2, 0), (2, 2)]),
+            Rotation::Right => ([(0, 2), (2, 2)], [(0, 0), (2, 0)]),
+            Rotation::Reverse => ([(2, 0), (2, 2)], [(0, 0), (0, 2)]),
+            Rotation::Left => ([(0, 0), (2, 0)], [(0, 2), (2, 2)]),
+        };
+        let front = front.into_iter().filter(|&cell| corner(cell)).count();
+        let back = back.into_iter().filter(|&cell| corner(cell)).count();
+        if front + back < 3 {
+            return None;
+        }
+        if front == 2 || self.far_kick {
+            Some(TSpin::Full)
+        } else {
+            Some(TSpin::Mini)
+        }
+    }
+    /// Drop the currently falling piece down one row
+    ///
+    /// A blocked piece locks once its lock delay has run out, see
+    /// [`GameState::lock_when_due`].
+    ///
+    /// # Returns
+    /// * Whether the piece locked and whether the game is over
+    fn drop_piece(&mut self) -> StepOutcome {
+        if self.try_move(|piece| piece.loc.row += 1) {
+            self.fell();
+            return StepOutcome::default();
+        }
+        self.lock_when_due()
+    }
+    /// Lock the blocked falling piece once its lock delay has run out
+    ///
+    /// Starts the delay if it has not started yet. The piece locks straight
+    /// away when there is no delay or no resets are left.
+    ///
+    /// # Returns
+    /// * Whether the piece locked and whether the game is over
+    fn lock_when_due(&mut self) -> StepOutcome {
+        let delay = self.rules.lock_delay;
+        match self.lock_started {
+            Some(start) if self.clock.wrapping_sub(start) < delay.ms as u32 => {
+                return StepOutcome::default();
+            }
+            None if delay.ms > 0 && self.lock_resets < delay.max_resets => {
+                self.lock_started = Some(self.clock);
+                return StepOutcome::default();
+            }
+            _ => {}
+        }
+        let outcome = self.lock_piece();
+        self.award(outcome)
+    }
+    /// Lock the falling piece in place and clear any full rows
+    ///
+    /// The game is over if the piece locked entirely in the hidden rows, or if
+    /// any block is left in the top row once the full rows have cleared.
+    ///
+    /// # Returns
+    /// * A locked piece outcome, with the rows cleared or the reason the game is over
+    fn lock_piece(&mut self) -> StepOutcome {
+        let hidden_rows = self.rules.hidden_rows as isize;
+        let locked_out = self
+            .falling
+            .is_some_and(|piece| piece.cells().all(|(row, _)| row < hidden_rows));
+        let t_spin = self.t_spin();
+        let mut outcome = StepOutcome {
+            t_spin,
+            ..self.add_piece()
+        };
+        if locked_out {
+            return StepOutcome {
+                piece_locked: true,
+                ..self.end_game(GameOverReason::LockOut)
+            };
+        }
+        outcome.lines_cleared = self.check_rows();
+        outcome.all_clear =
+            outcome.lines_cleared > 0 && !self.board.rows().flatten().any(|cell| cell.is_locked());
+        self.count_chains(&mut outcome);
+        // Check if any solid blocks are left in the top row
+        if self.check_column() {
+            outcome.game_over = self.end_game(GameOverReason::TopOut).game_over;
+        }
+        outcome
+    }
+    /// Update the combo and back-to-back counters after a piece locks
+    ///
+    /// A lock without a clear ends the combo. An easier clear ends the
+    /// back-to-back chain, but a lock without a clear leaves it alone.
+    ///
+    /// # Arguments
+    /// * `outcome` - The lock, which gets the new counters
+    fn count_chains(&mut self, outcome: &mut StepOutcome) {
+        if outcome.lines_cleared == 0 {
+            self.combo = None;
+            return;
+        }
+        self.combo = Some(self.combo.map_or(0, |combo| combo.saturating_add(1)));
+        self.back_to_back = if outcome.difficult(&self.rules.scoring) {
+            Some(self.back_to_back.map_or(0, |chain| chain.saturating_add(1)))
+        } else {
+            None
+        };
+        outcome.combo = self.combo;
+        outcome.back_to_back = self.back_to_back;
+    }
+    /// Score the clears and drops of an outcome and update the lines and level
+    ///
+    /// # Arguments
+    /// * `outcome` - What happened
+    ///
+    /// # Returns
+    /// * The same outcome with the points scored
+    fn award(&mut self, mut outcome: StepOutcome) -> StepOutcome {
+        let table = self.rules.scoring;
+        let mut clear = table.clear_points(outcome.lines_cleared, outcome.t_spin, self.level);
+        let back_to_back = outcome.back_to_back.is_some_and(|chain| chain > 0);
+        if back_to_back {
+            clear = table.back_to_back_points(clear);
+        }
+        // The all-clear bonus stays out of the back-to-back bonus
+        if outcome.all_clear {
+            clear = clear.saturating_add(table.all_clear_points(
+                outcome.lines_cleared,
+                back_to_back,
+                self.level,
+            ));
+        }
+        outcome.points = clear
+            + table.combo_points(outcome.combo.unwrap_or_default(), self.level)
+            + table.drop_points(outcome.soft_drop, outcome.hard_drop);
+        self.score = self.score.saturating_add(outcome.points);
+        self.lines += outcome.lines_cleared as u32;
+        self.level = table.level(self.lines);
+        outcome
+    }
+    /// Add the currently falling piece to the solid blocks on the board
+    /// and reset the falling piece to nothing.
+    ///
+    /// # Returns
+    /// * A locked piece outcome
+    fn add_piece(&mut self) -> StepOutcome {
+        if let Some(piece) = self.falling {
+            self.paint_piece(Cell::Active, Cell::Locked(piece.kind));
+        }
+        let outcome = StepOutcome {
+            piece_locked: true,
+            ..StepOutcome::default()
+        };
+        // Reset the falling piece
+        self.falling = None;
+        self.hold_used = false;
+        outcome
+    }
+    /// Function to check for full rows, clear them, and drop the rest down
+    ///
+    /// How the rest falls follows the line clear rule. Sticky and cascade
+    /// gravity can fill more rows as blocks fall, which clear as a chain.
+    ///
+    /// # Returns
+    /// * The number of rows cleared, including chains
+    pub fn check_rows(&mut self) -> u8 {
+        let mut count: u8 = 0;
+        loop {
+            let cleared = self.board.clear_full_rows();
+            let rows = cleared.iter().filter(|&&row| row).count() as u8;
+            // If no rows are full then return
+            if rows == 0 {
+                return count;
+            }
+            count += rows;
+            let moved = match self.rules.line_clear {
+                LineClear::Naive => {
+                    self.board.collapse_rows(&cleared);
+                    false
+                }
+                LineClear::Sticky => self.board.drop_chunks(),
+                LineClear::Cascade => self.board.drop_cells(),
+            };
+            // Only falling blocks can fill another row
+            if !moved {
+                return count;
+            }
+        }
+    }
+    /// Check if there are any solid blocks in the top row
+    ///
+    /// # Returns
+    /// * True if there are blocks in the top row, false otherwise
+    fn check_column(&self) -> bool {
+        self.board[0].iter().any(|cell| cell.is_locked())
+    }
+    /// Draw pieces until the queue holds as many as the rules preview
+    ///
+    /// # Arguments
+    /// * `rng` - The random number generator to draw pieces with
+    fn fill_queue(&mut self, rng: &mut Pcg64) {
+        while self.queue.len() < self.rules.preview.min(MAX_PREVIEW) {
+            self.queue.push(self.randomizer.next_piece(rng));
+        }
+    }
+    /// Step the game state forward one frame
+    ///
+    /// # Arguments
+    /// * `seed` - The seed for the random number generator
+    ///
+    /// # Returns
+    /// * What happened during the step, see [`StepOutcome`]
+    pub fn step(&mut self, seed: u128) -> StepOutcome {
+        if self.game_over.is_some() {
+            return StepOutcome {
+                game_over: self.game_over,
+                ..StepOutcome::default()
+            };
+        }
+        let mut rng = Pcg64::new_seed(seed);
+        self.fill_queue(&mut rng);
+        if self.falling.is_none() {
+            let kind = match self.queue.pop() {
+                Some(kind) => kind,
+                None => self.randomizer.next_piece(&mut rng),
+            };
+            self.fill_queue(&mut rng);
+            return self.spawn_piece(kind);
+        }
+        self.drop_piece()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::Scheduler;
+    use crate::tetrominos::PieceSet;
+
+    /// Shorthand for an empty cell
+    const E: Cell = Cell::Empty;
+
+    /// Shorthand for a cell of the falling piece
+    const A: Cell = Cell::Active;
+
+    /// Shorthand for a locked cell
+    const X: Cell = Cell::Locked(PieceKind::O);
+
+    /// Run a generic test on the LED grid, a standard board, and an odd sized board
+    macro_rules! on_all_boards {
+        ($test:ident) => {
+            $test::<5, 5>();
+            $test::<10, 20>();
+            $test::<7, 11>();
+        };
+    }
+
+    /// Location of the falling piece
+    fn loc<const W: usize, const H: usize>(game: &GameState<W, H>) -> PieceLocation {
+        game.falling_piece().unwrap().loc
+    }
+
+    /// Column micro pieces spawn in
+    fn spawn_col<const W: usize>() -> usize {
+        (W - 1) / 2
+    }
+
+    /// Row with locked blocks in every column except the given ones
+    fn row_except<const W: usize>(gaps: &[usize]) -> [Cell; W] {
+        let mut row = [X; W];
+        for &col in gaps {
+            row[col] = E;
+        }
+        row
+    }
+
+    /// Count the cells on the board that are not empty
+    fn filled<const W: usize, const H: usize>(game: &GameState<W, H>) -> usize {
+        game.board()
+            .rows()
+            .flatten()
+            .filter(|&&cell| cell != E)
+            .count()
+    }
+
+    /// Let a gravity interval pass and step, as the main loop does
+    fn tick<const W: usize, const H: usize>(game: &mut GameState<W, H>) -> StepOutcome {
+        game.advance(game.gravity_interval() as u32);
+        game.step(0)
+    }
+
+    /// Step until the falling piece rests on the stack or the floor
+    fn step_until_grounded<const W: usize, const H: usize>(game: &mut GameState<W, H>) {
+        while !game.grounded() {
+            tick(game);
+        }
+    }
+
+    /// Step until the falling piece locks
+    fn step_until_locked<const W: usize, const H: usize>(
+        game: &mut GameState<W, H>,
+    ) -> StepOutcome {
+        loop {
+            let outcome = tick(game);
+            if outcome.piece_locked {
+                return outcome;
+            }
+        }
+    }
+
+    fn step_spawns_piece_at_top<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        let outcome = game.step(1337);
+        assert!(outcome.piece_spawned);
+        assert!(!outcome.piece_locked);
+        assert_eq!(outcome.game_over, None);
+        let piece = game.falling_piece().unwrap();
+        assert_eq!(piece.rotation, Rotation::Spawn);
+        assert_eq!(
+            piece.loc,
+            PieceLocation {
+                row: 0,
+                col: spawn_col::<W>() as isize
+            }
+        );
+        assert!(game.board().rows().skip(2).flatten().all(|&cell| cell == E));
+    }
+
+    #[test]
+    fn step_spawns_piece_at_top_on_all_boards() {
+        on_all_boards!(step_spawns_piece_at_top);
+    }
+
+    fn piece_falls_until_floor_then_locks<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        game.spawn_piece(PieceKind::O);
+        let col = spawn_col::<W>();
+        for row in 1..H - 1 {
+            assert_eq!(tick(&mut game), StepOutcome::default());
+            assert_eq!(loc(&game).row, row as isize);
+        }
+        let outcome = tick(&mut game);
+        assert!(outcome.piece_locked);
+        assert_eq!(outcome.lines_cleared, 0);
+        assert_eq!(outcome.game_over, None);
+        assert_eq!(game.board[H - 2][col..col + 2], [X, X]);
+        assert_eq!(game.board[H - 1][col..col + 2], [X, X]);
+        assert_eq!(filled(&game), 4);
+        assert!(game.falling_piece().is_none());
+    }
+
+    #[test]
+    fn piece_falls_until_floor_then_locks_on_all_boards() {
+        on_all_boards!(piece_falls_until_floor_then_locks);
+    }
+
+    fn piece_locks_on_top_of_blocks<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        let col = spawn_col::<W>();
+        game.board[H - 1][col] = X;
+        game.spawn_piece(PieceKind::O);
+        step_until_locked(&mut game);
+        assert_eq!(game.board[H - 1][col..col + 2], [X, E]);
+        assert_eq!(game.board[H - 2][col..col + 2], [X, X]);
+        assert_eq!(game.board[H - 3][col..col + 2], [X, X]);
+    }
+
+    #[test]
+    fn piece_locks_on_top_of_blocks_on_all_boards() {
+        on_all_boards!(piece_locks_on_top_of_blocks);
+    }
+
+    fn locked_cells_keep_piece_kind<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        game.spawn_piece(PieceKind::L);
+        step_until_locked(&mut game);
+        let cells = || game.board().rows().flatten();
+        assert_eq!(cells().filter(|cell| cell.is_locked()).count(), 3);
+        assert!(cells().all(|&cell| cell == E || cell == Cell::Locked(PieceKind::L)));
+    }
+
+    #[test]
+    fn locked_cells_keep_piece_kind_on_all_boards() {
+        on_all_boards!(locked_cells_keep_piece_kind);
+    }
+
+    fn moves_stop_at_walls<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        game.spawn_piece(PieceKind::O);
+        for _ in 0..W {
+            game.move_left();
+        }
+        assert_eq!(loc(&game).col, 0);
+        assert_eq!(game.board[1][..2], [A, A]);
+        for _ in 0..W {
+            game.move_right();
+        }
+        assert_eq!(loc(&game).col, W as isize - 2);
+        assert_eq!(game.board[1][W - 2..], [A, A]);
+        assert_eq!(filled(&game), 4);
+    }
+
+    #[test]
+    fn moves_stop_at_walls_on_all_boards() {
+        on_all_boards!(moves_stop_at_walls);
+    }
+
+    fn moves_stop_at_solid_blocks<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        game.board[1][0] = X;
+        game.spawn_piece(PieceKind::O);
+        for _ in 0..W {
+            game.move_left();
+        }
+        assert_eq!(loc(&game).col, 1);
+        assert_eq!(game.board[1][..3], [X, A, A]);
+    }
+
+    #[test]
+    fn moves_stop_at_solid_blocks_on_all_boards() {
+        on_all_boards!(moves_stop_at_solid_blocks);
+    }
+
+    fn narrow_piece_reaches_right_wall<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        game.spawn_piece(PieceKind::I);
+        for _ in 0..W {
+            game.move_right();
+        }
+        assert_eq!(loc(&game).col, W as isize - 1);
+        assert_eq!(game.board[0][W - 1], A);
+        assert_eq!(game.board[1][W - 1], A);
+        // Lying flat would stick out past the wall, so it kicks left
+        game.rotate_piece();
+        assert_eq!(game.falling_piece().unwrap().rotation, Rotation::Right);
+        assert_eq!(loc(&game).col, W as isize - 2);
+        assert_eq!(game.board[0][W - 2..], [A, A]);
+        assert_eq!(filled(&game), 2);
+    }
+
+    #[test]
+    fn narrow_piece_reaches_right_wall_on_all_boards() {
+        on_all_boards!(narrow_piece_reaches_right_wall);
+    }
+
+    fn check_rows_clears_full_rows_and_drops_blocks<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        game.board[H - 3][0] = Cell::Locked(PieceKind::T);
+        game.board[H - 2] = [X; W];
+        game.board[H - 1] = [X; W];
+        assert_eq!(game.check_rows(), 2);
+        assert_eq!(game.board[H - 1][0], Cell::Locked(PieceKind::T));
+        assert_eq!(filled(&game), 1);
+    }
+
+    #[test]
+    fn check_rows_clears_full_rows_and_drops_blocks_on_all_boards() {
+        on_all_boards!(check_rows_clears_full_rows_and_drops_blocks);
+    }
+
+    fn check_rows_ignores_partial_rows<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        game.board[H - 1] = row_except(&[2]);
+        assert_eq!(game.check_rows(), 0);
+        assert_eq!(game.board[H - 1], row_except(&[2]));
+    }
+
+    #[test]
+    fn check_rows_ignores_partial_rows_on_all_boards() {
+        on_all_boards!(check_rows_ignores_partial_rows);
+    }
+
+    fn check_rows_ignores_falling_piece<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        game.board[H - 1] = [X; W];
+        game.board[H - 1][0] = A;
+        assert_eq!(game.check_rows(), 0);
+    }
+
+    #[test]
+    fn check_rows_ignores_falling_piece_on_all_boards() {
+        on_all_boards!(check_rows_ignores_falling_piece);
+    }
+
+    /// Board with a full row between a hanging group of blocks and a gappy row
+    const CLEAR_MODES_BOARD: [&str; 4] = [".##..", ".#...", "#####", "#..##"];
+
+    /// Game on the 5x5 board with the given line clear rule
+    fn line_clear_game(line_clear: LineClear, rows: &[&str]) -> GameState<5, 5> {
+        let mut game = GameState::with_rules(Rules {
+            line_clear,
+            ..Rules::MICRO
+        });
+        game.board = Board::from_rows(rows);
+        game
+    }
+
+    #[test]
+    fn naive_clear_shifts_whole_rows() {
+        let mut game = line_clear_game(LineClear::Naive, &CLEAR_MODES_BOARD);
+        assert_eq!(game.check_rows(), 1);
+        // The overhang stays in the air
+        assert_eq!(game.board, Board::from_rows(&[".##..", ".#...", "#..##"]));
+    }
+
+    #[test]
+    fn naive_clear_shifts_by_rows_cleared_below() {
+        let rows = ["#....", "#####", ".#...", "#####", "#.#.#"];
+        let mut game = line_clear_game(LineClear::Naive, &rows);
+        assert_eq!(game.check_rows(), 2);
+        assert_eq!(game.board, Board::from_rows(&["#....", ".#...", "#.#.#"]));
+    }
+
+    #[test]
+    fn sticky_clear_drops_connected_groups() {
+        let mut game = line_clear_game(LineClear::Sticky, &CLEAR_MODES_BOARD);
+        assert_eq!(game.check_rows(), 1);
+        // The group falls until its lowest block lands, keeping its shape
+        assert_eq!(game.board, Board::from_rows(&[".##..", "##.##"]));
+    }
+
+    #[test]
+    fn sticky_clear_chains_when_a_group_fills_a_row() {
+        let rows = [".##..", "..#..", "#####", "##.##", "#..##"];
+        let mut game = line_clear_game(LineClear::Sticky, &rows);
+        // The group catches on its arm and fills the gap below, then what is
+        // left of it falls again and fills the bottom row
+        assert_eq!(game.check_rows(), 3);
+        assert_eq!(game.board, Board::new());
+    }
+
+    #[test]
+    fn cascade_clear_drops_every_block_and_chains() {
+        let mut game = line_clear_game(LineClear::Cascade, &CLEAR_MODES_BOARD);
+        assert_eq!(game.check_rows(), 2);
+        assert_eq!(game.board, Board::from_rows(&[".#..."]));
+    }
+
+    fn step_reports_lines_cleared_on_lock<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        let col = spawn_col::<W>();
+        game.board[H - 2] = row_except(&[col, col + 1]);
+        game.board[H - 1] = row_except(&[col, col + 1]);
+        game.spawn_piece(PieceKind::O);
+        let outcome = step_until_locked(&mut game);
+        assert_eq!(outcome.lines_cleared, 2);
+        assert_eq!(filled(&game), 0);
+    }
+
+    #[test]
+    fn step_reports_lines_cleared_on_lock_on_all_boards() {
+        on_all_boards!(step_reports_lines_cleared_on_lock);
+    }
+
+    fn locking_in_top_row_is_game_over<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        let col = spawn_col::<W>();
+        for row in 2..H {
+            game.board[row][col] = X;
+        }
+        game.spawn_piece(PieceKind::O);
+        let outcome = tick(&mut game);
+        assert!(outcome.piece_locked);
+        assert_eq!(outcome.game_over, Some(GameOverReason::TopOut));
+        assert_eq!(game.board[0][col..col + 2], [X, X]);
+    }
+
+    #[test]
+    fn locking_in_top_row_is_game_over_on_all_boards() {
+        on_all_boards!(locking_in_top_row_is_game_over);
+    }
+
+    fn spawning_onto_blocks_is_block_out<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        let col = spawn_col::<W>();
+        game.board[1][col] = X;
+        let before = game.board;
+        let outcome = game.spawn_piece(PieceKind::O);
+        assert_eq!(outcome.game_over, Some(GameOverReason::BlockOut));
+        assert!(!outcome.piece_spawned);
+        // The piece is not merged into the blocks
+        assert_eq!(game.board, before);
+        assert!(game.falling_piece().is_none());
+        assert_eq!(tick(&mut game).game_over, Some(GameOverReason::BlockOut));
+    }
+
+    #[test]
+    fn spawning_onto_blocks_is_block_out_on_all_boards() {
+        on_all_boards!(spawning_onto_blocks_is_block_out);
+    }
+
+    #[test]
+    fn step_reports_block_out() {
+        let mut game = GameState::<5, 5>::new();
+        game.board = Board::from_rows(&["#####", "#.#.#", "#.#.#", "#.#.#", "#.#.#"]);
+        let outcome = tick(&mut game);
+        assert_eq!(outcome.game_over, Some(GameOverReason::BlockOut));
+        assert_eq!(game.game_over(), Some(GameOverReason::BlockOut));
+    }
+
+    #[test]
+    fn hold_onto_blocks_is_block_out() {
+        let mut game = GameState::<5, 5>::new();
+        game.held = Some(PieceKind::I);
+        game.spawn_piece(PieceKind::O);
+        game.try_move(|piece| piece.loc.row += 2);
+        let col = spawn_col::<5>();
+        game.board[0][col..col + 2].copy_from_slice(&[X, X]);
+        game.board[1][col..col + 2].copy_from_slice(&[X, X]);
+        let outcome = game.hold();
+        assert!(outcome.piece_held);
+        assert_eq!(outcome.game_over, Some(GameOverReason::BlockOut));
+    }
+
+    #[test]
+    fn locking_in_hidden_rows_is_lock_out() {
+        let mut game = GameState::<10, 22>::with_rules(Rules::GUIDELINE);
+        for row in 2..22 {
+            game.board[row][4] = X;
+        }
+        game.spawn_piece(PieceKind::O);
+        let outcome = game.hard_drop();
+        assert!(outcome.piece_locked);
+        assert_eq!(outcome.game_over, Some(GameOverReason::LockOut));
+        assert_eq!(game.game_over(), Some(GameOverReason::LockOut));
+    }
+
+    #[test]
+    fn locking_partly_visible_is_not_game_over() {
+        let mut game = GameState::<10, 22>::with_rules(Rules::GUIDELINE);
+        for row in 3..22 {
+            game.board[row][4] = X;
+        }
+        game.spawn_piece(PieceKind::O);
+        let outcome = game.hard_drop();
+        assert!(outcome.piece_locked);
+        assert_eq!(outcome.game_over, None);
+    }
+
+    #[test]
+    fn clearing_the_top_rows_avoids_top_out() {
+        let mut game = GameState::<5, 5>::new();
+        game.board = Board::from_rows(&["##..#", "###.#", "####.", "####."]);
+        game.spawn_piece(PieceKind::O);
+        let outcome = game.hard_drop();
+        assert_eq!(outcome.lines_cleared, 2);
+        assert_eq!(outcome.game_over, None);
+        assert!(game.board[0].iter().all(|&cell| cell == E));
+    }
+
+    fn srs_pieces_spawn_centered_and_lock_four_cells<const W: usize, const H: usize>() {
+        for &kind in PieceSet::Srs.kinds() {
+            let mut game = GameState::<W, H>::with_rules(Rules::GUIDELINE);
+            game.spawn_piece(kind);
+            let piece = *game.falling_piece().unwrap();
+            assert_eq!(piece.loc.col, ((W - piece.shape.size) / 2) as isize);
+            assert_eq!(filled(&game), 4);
+            step_until_locked(&mut game);
+            let locked = game
+                .board()
+                .rows()
+                .flatten()
+                .filter(|cell| cell.is_locked());
+            assert_eq!(locked.count(), 4, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn srs_pieces_spawn_centered_and_lock_four_cells_on_all_boards() {
+        on_all_boards!(srs_pieces_spawn_centered_and_lock_four_cells);
+    }
+
+    #[test]
+    fn srs_t_rotates_through_all_states() {
+        let mut game = GameState::<10, 20>::with_rules(Rules::GUIDELINE);
+        game.spawn_piece(PieceKind::T);
+        game.drop_piece();
+        for rotation in [
+            Rotation::Right,
+            Rotation::Reverse,
+            Rotation::Left,
+            Rotation::Spawn,
+        ] {
+            game.rotate_piece();
+            assert_eq!(game.falling_piece().unwrap().rotation, rotation);
+            assert_eq!(filled(&game), 4);
+        }
+    }
+
+    #[test]
+    fn srs_vertical_i_reaches_both_walls() {
+        let mut game = GameState::<10, 20>::with_rules(Rules::GUIDELINE);
+        game.spawn_piece(PieceKind::I);
+        game.rotate_piece();
+        for _ in 0..10 {
+            game.move_left();
+        }
+        // Filled column is third in the box, so the box hangs off the left wall
+        assert_eq!(loc(&game).col, -2);
+        assert!((0..4).all(|row| game.board[row][0] == A));
+        for _ in 0..10 {
+            game.move_right();
+        }
+        assert_eq!(loc(&game).col, 7);
+        assert!((0..4).all(|row| game.board[row][9] == A));
+    }
+
+    fn rotation_into_blocks_is_rejected<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        let col = spawn_col::<W>();
+        // Wall in a standing straight piece so no kick can lay it flat
+        for row in 0..H {
+            for c in [col - 1, col + 1] {
+                game.board[row][c] = X;
+            }
+        }
+        game.board[2][col] = X;
+        game.spawn_piece(PieceKind::I);
+        let before = game.board;
+        game.rotate_piece();
+        assert_eq!(game.falling_piece().unwrap().rotation, Rotation::Spawn);
+        assert_eq!(game.board, before);
+    }
+
+    #[test]
+    fn rotation_into_blocks_is_rejected_on_all_boards() {
+        on_all_boards!(rotation_into_blocks_is_rejected);
+    }
+
+    #[test]
+    fn micro_rotation_kicks_up_off_blocks() {
+        let mut game = GameState::<5, 5>::new();
+        game.board = Board::from_rows(&["..#..", "#...."]);
+        game.spawn_piece(PieceKind::L);
+        assert!(game.try_move(|piece| piece.loc = PieceLocation { row: 3, col: 1 }));
+        // In place and one right hit the block above, one left hits the floor block
+        game.rotate_piece();
+        assert_eq!(game.falling_piece().unwrap().rotation, Rotation::Right);
+        assert_eq!(loc(&game), PieceLocation { row: 2, col: 1 });
+        assert_eq!(filled(&game), 5);
+    }
+
+    #[test]
+    fn srs_t_kicks_up_off_the_floor() {
+        let mut game = GameState::<10, 20>::with_rules(Rules::GUIDELINE);
+        game.spawn_piece(PieceKind::T);
+        while game.try_move(|piece| piece.loc.row += 1) {}
+        assert_eq!(loc(&game), PieceLocation { row: 18, col: 3 });
+        // In place and one left both poke through the floor, so kick 3 is used
+        game.rotate_piece();
+        assert_eq!(game.falling_piece().unwrap().rotation, Rotation::Right);
+        assert_eq!(loc(&game), PieceLocation { row: 17, col: 2 });
+        assert_eq!(filled(&game), 4);
+    }
+
+    #[test]
+    fn srs_i_kicks_off_the_right_wall() {
+        let mut game = GameState::<10, 20>::with_rules(Rules::GUIDELINE);
+        game.spawn_piece(PieceKind::I);
+        game.drop_piece();
+        game.rotate_piece();
+        for _ in 0..10 {
+            game.move_right();
+        }
+        assert_eq!(loc(&game).col, 7);
+        game.rotate_piece();
+        assert_eq!(game.falling_piece().unwrap().rotation, Rotation::Reverse);
+        assert_eq!(loc(&game).col, 6);
+        assert_eq!(game.board[loc(&game).row as usize + 2][6..], [A; 4]);
+    }
+
+    #[test]
+    fn srs_t_rotates_counter_clockwise_and_180() {
+        let mut game = GameState::<10, 20>::with_rules(Rules::GUIDELINE);
+        game.spawn_piece(PieceKind::T);
+        game.drop_piece();
+        game.apply(Action::RotateCounterClockwise);
+        let piece = *game.falling_piece().unwrap();
+        assert_eq!(piece.rotation, Rotation::Left);
+        assert_eq!(
+            piece.shape,
+            PieceSet::Srs.shape(PieceKind::T).rotate_counter_clockwise()
+        );
+        game.apply(Action::Rotate180);
+        assert_eq!(game.falling_piece().unwrap().rotation, Rotation::Right);
+        game.apply(Action::Rotate180);
+        game.apply(Action::RotateClockwise);
+        assert_eq!(game.falling_piece().unwrap().rotation, Rotation::Spawn);
+        assert_eq!(loc(&game), PieceLocation { row: 1, col: 3 });
+        assert_eq!(filled(&game), 4);
+    }
+
+    #[test]
+    fn srs_180_kicks_up_off_the_floor() {
+        let mut game = GameState::<10, 20>::with_rules(Rules::GUIDELINE);
+        game.spawn_piece(PieceKind::T);
+        while game.try_move(|piece| piece.loc.row += 1) {}
+        // Pointing down in place would poke through the floor, so it kicks up
+        game.apply(Action::Rotate180);
+        assert_eq!(game.falling_piece().unwrap().rotation, Rotation::Reverse);
+        assert_eq!(loc(&game), PieceLocation { row: 17, col: 3 });
+    }
+
+    fn actions_move_the_piece<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        game.spawn_piece(PieceKind::L);
+        game.apply(Action::MoveLeft);
+        assert_eq!(loc(&game).col, spawn_col::<W>() as isize - 1);
+        game.apply(Action::MoveRight);
+        game.apply(Action::MoveRight);
+        assert_eq!(loc(&game).col, spawn_col::<W>() as isize + 1);
+        game.apply(Action::RotateCounterClockwise);
+        assert_eq!(game.falling_piece().unwrap().rotation, Rotation::Left);
+        assert_eq!(filled(&game), 3);
+    }
+
+    #[test]
+    fn actions_move_the_piece_on_all_boards() {
+        on_all_boards!(actions_move_the_piece);
+    }
+
+    /// Deadlines of a game loop that plays soft drops
+    #[derive(Clone, Copy)]
+    enum Due {
+        Gravity,
+        SoftDrop,
+    }
+
+    /// Run the deadlines up to a time, restarting gravity after each soft drop
+    /// like the firmware does
+    fn run_until<const W: usize, const H: usize>(
+        game: &mut GameState<W, H>,
+        schedule: &mut Scheduler<Due, 2>,
+        from: u32,
+        until: u32,
+    ) {
+        let interval = u32::from(game.gravity_interval());
+        for now in from..=until {
+            while let Some(due) = schedule.pop_due(now) {
+                let outcome = match due {
+                    Due::Gravity => {
+                        schedule.schedule_in(now, interval, Due::Gravity);
+                        game.step(0)
+                    }
+                    Due::SoftDrop => game.apply(Action::SoftDrop),
+                };
+                if outcome.soft_drop > 0 {
+                    schedule.schedule_in(now, interval, Due::Gravity);
+                }
+            }
+        }
+    }
+
+    fn soft_drop_moves_down_and_restarts_gravity<const W: usize, const H: usize>() {
+        let interval = u32::from(GameState::<W, H>::new().gravity_interval());
+        // Just before a gravity deadline, the soft drop replaces that row
+        let mut game = GameState::<W, H>::new();
+        game.spawn_piece(PieceKind::O);
+        let mut schedule = Scheduler::new();
+        schedule.schedule(interval, Due::Gravity);
+        schedule.schedule(interval - 1, Due::SoftDrop);
+        run_until(&mut game, &mut schedule, 0, interval);
+        assert_eq!(loc(&game).row, 1);
+        run_until(&mut game, &mut schedule, interval + 1, 2 * interval - 2);
+        assert_eq!(loc(&game).row, 1);
+        run_until(&mut game, &mut schedule, 2 * interval - 1, 2 * interval - 1);
+        assert_eq!(loc(&game).row, 2);
+        // Just after a gravity deadline, the next row is a full interval later
+        let mut game = GameState::<W, H>::new();
+        game.spawn_piece(PieceKind::O);
+        let mut schedule = Scheduler::new();
+        schedule.schedule(interval, Due::Gravity);
+        schedule.schedule(interval + 1, Due::SoftDrop);
+        run_until(&mut game, &mut schedule, 0, 2 * interval);
+        assert_eq!(loc(&game).row, 2);
+        run_until(&mut game, &mut schedule, 2 * interval + 1, 2 * interval + 1);
+        assert_eq!(loc(&game).row, 3);
+    }
+
+    #[test]
+    fn soft_drop_moves_down_and_restarts_gravity_on_all_boards() {
+        on_all_boards!(soft_drop_moves_down_and_restarts_gravity);
+    }
+
+    fn soft_drop_locks_on_the_floor<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        game.spawn_piece(PieceKind::O);
+        for _ in 0..H - 2 {
+            assert_eq!(game.soft_drop().soft_drop, 1);
+        }
+        // Blocked soft drops wait out the lock delay like gravity does
+        let delay = game.rules().lock_delay.ms as u32;
+        assert_eq!(game.soft_drop(), StepOutcome::default());
+        game.advance(delay - 1);
+        assert_eq!(game.soft_drop(), StepOutcome::default());
+        assert!(game.falling_piece().is_some());
+        game.advance(1);
+        let outcome = game.soft_drop();
+        assert_eq!(outcome.soft_drop, 0);
+        assert!(outcome.piece_locked);
+        assert!(game.falling_piece().is_none());
+        assert_eq!(game.board[H - 1][spawn_col::<W>()], X);
+    }
+
+    #[test]
+    fn soft_drop_locks_on_the_floor_on_all_boards() {
+        on_all_boards!(soft_drop_locks_on_the_floor);
+    }
+
+    fn hard_drop_locks_at_landing_row<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        let col = spawn_col::<W>();
+        game.board[H - 1][col + 1] = X;
+        game.spawn_piece(PieceKind::O);
+        let outcome = game.apply(Action::HardDrop);
+        assert!(outcome.piece_locked);
+        assert_eq!(outcome.hard_drop, H as u8 - 3);
+        assert_eq!(game.board[H - 2][col..col + 2], [X, X]);
+        assert_eq!(game.board[H - 3][col..col + 2], [X, X]);
+        assert!(game.falling_piece().is_none());
+        assert!(tick(&mut game).piece_spawned);
+    }
+
+    #[test]
+    fn hard_drop_locks_at_landing_row_on_all_boards() {
+        on_all_boards!(hard_drop_locks_at_landing_row);
+    }
+
+    fn hard_drop_clears_lines<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        let col = spawn_col::<W>();
+        game.board[H - 2] = row_except(&[col, col + 1]);
+        game.board[H - 1] = row_except(&[col, col + 1]);
+        game.spawn_piece(PieceKind::O);
+        let outcome = game.hard_drop();
+        assert_eq!(outcome.hard_drop, H as u8 - 2);
+        assert_eq!(outcome.lines_cleared, 2);
+        assert_eq!(filled(&game), 0);
+    }
+
+    #[test]
+    fn hard_drop_clears_lines_on_all_boards() {
+        on_all_boards!(hard_drop_clears_lines);
+    }
+
+    fn drops_and_clears_score_points<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        let table = ScoreTable::MICRO;
+        game.spawn_piece(PieceKind::O);
+        assert_eq!(game.soft_drop().points, table.soft_drop);
+        let col = spawn_col::<W>();
+        game.board[H - 2] = row_except(&[col, col + 1]);
+        game.board[H - 1] = row_except(&[col, col + 1]);
+        // Keep a block above the clear so it is not an all-clear
+        game.board[H - 3][0] = X;
+        let outcome = game.hard_drop();
+        let points = table.lines[2] + table.hard_drop * (H as u32 - 3);
+        assert_eq!(outcome.points, points);
+        assert_eq!(game.score(), table.soft_drop + points);
+        assert_eq!(game.lines(), 2);
+    }
+
+    #[test]
+    fn drops_and_clears_score_points_on_all_boards() {
+        on_all_boards!(drops_and_clears_score_points);
+    }
+
+    #[test]
+    fn clears_score_more_at_higher_levels() {
+        let mut game = GameState::<10, 20>::with_rules(Rules::GUIDELINE);
+        assert_eq!(game.level(), 1);
+        // Eight doubles with an upright I piece in a one wide well
+        for clear in 0..8 {
+            game.board[18] = row_except(&[5]);
+            game.board[19] = row_except(&[5]);
+            game.spawn_piece(PieceKind::I);
+            game.rotate_piece();
+            let outcome = game.hard_drop();
+            assert_eq!(outcome.lines_cleared, 2);
+            let level = 1 + clear * 2 / 10;
+            // Every double after the first also scores as a combo
+            let combo = 50 * clear * level;
+            assert_eq!(
+                outcome.points,
+                300 * level + combo + 2 * outcome.hard_drop as u32
+            );
+            // Clear the rest of the piece left above the cleared rows
+            game.board = Board::new();
+        }
+        assert_eq!(game.lines(), 16);
+        assert_eq!(game.level(), 2);
+        assert_eq!(game.gravity_interval(), 793);
+    }
+
+    #[test]
+    fn drops_do_nothing_without_a_piece() {
+        let mut game = GameState::<5, 5>::new();
+        assert_eq!(game.soft_drop(), StepOutcome::default());
+        assert_eq!(game.hard_drop(), StepOutcome::default());
+    }
+
+    #[test]
+    fn piece_locks_once_lock_delay_runs_out() {
+        let mut game = GameState::<10, 20>::with_rules(Rules::GUIDELINE);
+        game.spawn_piece(PieceKind::O);
+        while loc(&game).row < 18 {
+            tick(&mut game);
+        }
+        // Steps without time passing never lock a resting piece
+        assert_eq!(game.step(0), StepOutcome::default());
+        game.advance(499);
+        assert!(!game.step(0).piece_locked);
+        game.advance(1);
+        assert!(game.step(0).piece_locked);
+    }
+
+    #[test]
+    fn lock_deadline_counts_down_and_locks_on_time() {
+        let mut game = GameState::<10, 20>::with_rules(Rules::GUIDELINE);
+        game.spawn_piece(PieceKind::O);
+        assert_eq!(game.lock_remaining(), None);
+        step_until_grounded(&mut game);
+        assert_eq!(game.lock_remaining(), Some(500));
+        game.advance(300);
+        assert_eq!(game.lock_remaining(), Some(200));
+        assert_eq!(game.lock_if_due(), StepOutcome::default());
+        // A move restarts the delay from the time of the move
+        game.move_left();
+        assert_eq!(game.lock_remaining(), Some(500));
+        game.advance(500);
+        assert!(game.lock_if_due().piece_locked);
+        assert_eq!(game.lock_remaining(), None);
+    }
+
+    #[test]
+    fn slides_under_overhang_during_lock_delay() {
+        let mut game = GameState::<10, 20>::with_rules(Rules::GUIDELINE);
+        game.board = Board::from_rows(&[".....#####", "..........", ".........."]);
+        game.spawn_piece(PieceKind::O);
+        // Resting on the overhang, then stepping off the edge
+        step_until_grounded(&mut game);
+        game.move_left();
+        step_until_grounded(&mut game);
+        for _ in 0..2 {
+            game.advance(400);
+            game.step(0);
+            game.move_right();
+        }
+        assert!(!game.step(0).piece_locked);
+        assert!(step_until_locked(&mut game).piece_locked);
+        assert_eq!(game.board[18][5..7], [X, X]);
+        assert_eq!(game.board[19][5..7], [X, X]);
+    }
+
+    #[test]
+    fn move_resets_are_limited() {
+        let mut game = GameState::<10, 20>::with_rules(Rules::GUIDELINE);
+        game.spawn_piece(PieceKind::O);
+        step_until_grounded(&mut game);
+        for reset in 0..Rules::GUIDELINE.lock_delay.max_resets {
+            game.advance(400);
+            assert!(!game.step(0).piece_locked);
+            if reset % 2 == 0 {
+                game.move_left();
+            } else {
+                game.move_right();
+            }
+        }
+        // The last move no longer resets the delay
+        game.advance(400);
+        game.move_left();
+        game.advance(100);
+        assert!(game.step(0).piece_locked);
+    }
+
+    #[test]
+    fn step_reset_ignores_moves() {
+        let mut game = GameState::<5, 5>::new();
+        assert_eq!(game.rules().lock_delay.reset, LockReset::Step);
+        game.spawn_piece(PieceKind::O);
+        step_until_grounded(&mut game);
+        game.advance(400);
+        game.move_left();
+        game.advance(100);
+        assert!(game.step(0).piece_locked);
+    }
+
+    #[test]
+    fn zero_lock_delay_locks_on_contact() {
+        let mut rules = Rules::GUIDELINE;
+        rules.lock_delay.ms = 0;
+        let mut game = GameState::<10, 20>::with_rules(rules);
+        game.spawn_piece(PieceKind::O);
+        step_until_grounded(&mut game);
+        assert!(game.step(0).piece_locked);
+    }
+
+    #[test]
+    fn game_over_stops_the_game() {
+        let mut game = GameState::<5, 5>::new();
+        game.board = Board::from_rows(&["..#..", "..#..", "..#.."]);
+        game.spawn_piece(PieceKind::O);
+        let outcome = game.hard_drop();
+        assert_eq!(outcome.game_over, Some(GameOverReason::TopOut));
+        assert_eq!(game.game_over(), Some(GameOverReason::TopOut));
+        let before = game.board;
+        assert_eq!(tick(&mut game).game_over, Some(GameOverReason::TopOut));
+        game.move_left();
+        assert_eq!(game.soft_drop(), StepOutcome::default());
+        assert_eq!(game.board, before);
+    }
+
+    #[test]
+    fn outcomes_combine_in_order() {
+        let dropped = StepOutcome {
+            lines_cleared: 1,
+            piece_locked: true,
+            hard_drop: 3,
+            ..StepOutcome::default()
+        };
+        let stepped = StepOutcome {
+            piece_spawned: true,
+            game_over: Some(GameOverReason::TopOut),
+            ..StepOutcome::default()
+        };
+        assert_eq!(
+            dropped.then(stepped),
+            StepOutcome {
+                lines_cleared: 1,
+                piece_locked: true,
+                piece_spawned: true,
+                game_over: Some(GameOverReason::TopOut),
+                soft_drop: 0,
+                hard_drop: 3,
+                piece_held: false,
+                points: 0,
+                t_spin: None,
+                all_clear: false,
+                combo: None,
+                back_to_back: None,
+            }
+        );
+    }
+
+    #[test]
+    fn t_spin_double_scores_full_t_spin() {
+        let mut game = GameState::<10, 20>::with_rules(Rules::GUIDELINE);
+        game.board = Board::from_rows(&["...#......", "###...####", "####.#####"]);
+        game.spawn_piece(PieceKind::T);
+        game.rotate_piece();
+        step_until_grounded(&mut game);
+        assert_eq!(loc(&game), PieceLocation { row: 17, col: 3 });
+        // Twist the point down into the slot
+        game.rotate_piece();
+        let outcome = game.hard_drop();
+        assert_eq!(outcome.t_spin, Some(TSpin::Full));
+        assert_eq!(outcome.lines_cleared, 2);
+        assert!(!outcome.all_clear);
+        assert_eq!(outcome.points, 1200);
+    }
+
+    /// T pointing up on the floor of a standard board with one corner above
+    /// it filled, as if it had just rotated into place
+    fn mini_t_spin_game() -> GameState<10, 20> {
+        let mut game = GameState::<10, 20>::with_rules(Rules::GUIDELINE);
+        game.board = Board::from_rows(&["...#......", ".........."]);
+        game.spawn_piece(PieceKind::T);
+        game.try_move(|piece| piece.loc.row = 18);
+        game.rotated = true;
+        game
+    }
+
+    #[test]
+    fn one_front_corner_is_a_mini_t_spin() {
+        let mut game = mini_t_spin_game();
+        let outcome = game.hard_drop();
+        assert_eq!(outcome.t_spin, Some(TSpin::Mini));
+        assert_eq!(outcome.lines_cleared, 0);
+        assert_eq!(outcome.points, 100);
+    }
+
+    #[test]
+    fn far_kick_upgrades_a_mini_t_spin() {
+        let mut game = mini_t_spin_game();
+        game.far_kick = true;
+        assert_eq!(game.hard_drop().t_spin, Some(TSpin::Full));
+    }
+
+    #[test]
+    fn moving_after_a_rotation_is_not_a_t_spin() {
+        let mut game = mini_t_spin_game();
+        game.move_right();
+        game.move_left();
+        assert_eq!(game.hard_drop().t_spin, None);
+    }
+
+    #[test]
+    fn micro_pieces_never_t_spin() {
+        let mut game = GameState::<5, 5>::new();
+        game.board = Board::from_rows(&["#...#", "#...#"]);
+        game.spawn_piece(PieceKind::T);
+        game.rotate_piece();
+        assert_eq!(game.hard_drop().t_spin, None);
+    }
+
+    #[test]
+    fn emptying_the_board_is_an_all_clear() {
+        let mut game = GameState::<5, 5>::new();
+        game.board = Board::from_rows(&["##..#", "##..#"]);
+        game.spawn_piece(PieceKind::O);
+        let outcome = game.hard_drop();
+        assert_eq!(outcome.lines_cleared, 2);
+        assert!(outcome.all_clear);
+        assert_eq!(filled(&game), 0);
+        let table = ScoreTable::MICRO;
+        assert_eq!(
+            outcome.points,
+            800 + 2000 + table.drop_points(0, outcome.hard_drop)
+        );
+        // Back-to-back, only the clear gets the bonus and the all-clear has
+        // its own value
+        game.board = Board::from_rows(&["##..#", "##..#"]);
+        game.spawn_piece(PieceKind::O);
+        let outcome = game.hard_drop();
+        assert!(outcome.all_clear);
+        assert_eq!(outcome.back_to_back, Some(1));
+        assert_eq!(
+            outcome.points,
+            1200 + 3200 + table.combo_points(1, 1) + table.drop_points(0, outcome.hard_drop)
+        );
+    }
+
+    /// Lay the board out, then spawn a piece, turn it clockwise, shift it,
+    /// and hard drop it
+    fn play_script<const W: usize, const H: usize>(
+        game: &mut GameState<W, H>,
+        rows: &[&str],
+        kind: PieceKind,
+        turns: usize,
+        shift: isize,
+    ) -> StepOutcome {
+        game.board = Board::from_rows(rows);
+        game.spawn_piece(kind);
+        for _ in 0..turns {
+            game.rotate_piece();
+        }
+        for _ in 0..shift.abs() {
+            if shift < 0 {
+                game.move_left();
+            } else {
+                game.move_right();
+            }
+        }
+        game.hard_drop()
+    }
+
+    /// Four rows with a well in the right column, under a block that keeps
+    /// the tetris from being an all-clear
+    const TETRIS_WELL: [&str; 5] = [
+        "#.........",
+        "#########.",
+        "#########.",
+        "#########.",
+        "#########.",
+    ];
+
+    #[test]
+    fn consecutive_clears_count_combo_and_back_to_back() {
+        let mut game = GameState::<10, 20>::with_rules(Rules::GUIDELINE);
+        let tetris = play_script(&mut game, &TETRIS_WELL, PieceKind::I, 1, 4);
+        assert_eq!(tetris.lines_cleared, 4);
+        assert_eq!(tetris.combo, Some(0));
+        assert_eq!(tetris.back_to_back, Some(0));
+        let again = play_script(&mut game, &TETRIS_WELL, PieceKind::I, 1, 4);
+        assert_eq!(again.combo, Some(1));
+        assert_eq!(again.back_to_back, Some(1));
+        // Half again for the back-to-back tetris, plus one combo step
+        assert_eq!(again.points, 1200 + 50 + 2 * again.hard_drop as u32);
+        let single = play_script(&mut game, &["#########."], PieceKind::I, 1, 4);
+        assert_eq!(single.lines_cleared, 1);
+        assert_eq!(single.combo, Some(2));
+        assert_eq!(single.back_to_back, None);
+        let miss = play_script(&mut game, &[], PieceKind::O, 0, 0);
+        assert_eq!(miss.combo, None);
+        assert_eq!(miss.back_to_back, None);
+        let fresh = play_script(&mut game, &["#########."], PieceKind::I, 1, 4);
+        assert_eq!(fresh.combo, Some(0));
+    }
+
+    #[test]
+    fn locks_without_clears_keep_back_to_back() {
+        let mut game = GameState::<10, 20>::with_rules(Rules::GUIDELINE);
+        play_script(&mut game, &TETRIS_WELL, PieceKind::I, 1, 4);
+        let miss = play_script(&mut game, &[], PieceKind::O, 0, 0);
+        assert_eq!(miss.combo, None);
+        let tetris = play_script(&mut game, &TETRIS_WELL, PieceKind::I, 1, 4);
+        assert_eq!(tetris.combo, Some(0));
+        assert_eq!(tetris.back_to_back, Some(1));
+    }
+
+    #[test]
+    fn micro_doubles_chain_back_to_back() {
+        let mut game = GameState::<5, 5>::new();
+        let col = spawn_col::<5>();
+        let rows = ["##..#", "##..#"];
+        assert_eq!(col, 2);
+        let first = play_script(&mut game, &rows, PieceKind::O, 0, 0);
+        assert_eq!(first.back_to_back, Some(0));
+        let second = play_script(&mut game, &rows, PieceKind::O, 0, 0);
+        assert_eq!(second.combo, Some(1));
+        assert_eq!(second.back_to_back, Some(1));
+        let single = play_script(&mut game, &["##..#"], PieceKind::O, 0, 0);
+        assert_eq!(single.combo, Some(2));
+        assert_eq!(single.back_to_back, None);
+    }
+
+    #[test]
+    fn difficult_clears_are_the_biggest_clears_or_t_spins() {
+        let clear = |lines_cleared, t_spin| StepOutcome {
+            lines_cleared,
+            t_spin,
+            ..StepOutcome::default()
+        };
+        let guideline = ScoreTable::GUIDELINE;
+        assert!(clear(4, None).difficult(&guideline));
+        assert!(!clear(3, None).difficult(&guideline));
+        assert!(clear(1, Some(TSpin::Mini)).difficult(&guideline));
+        assert!(!clear(0, Some(TSpin::Full)).difficult(&guideline));
+        assert!(clear(2, None).difficult(&ScoreTable::MICRO));
+    }
+
+    fn hold_into_empty_slot_spawns_next_piece<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        game.spawn_piece(PieceKind::L);
+        game.drop_piece();
+        let outcome = game.apply(Action::Hold);
+        assert!(outcome.piece_held);
+        assert_eq!(game.held_piece(), Some(PieceKind::L));
+        assert!(game.falling_piece().is_none());
+        assert_eq!(filled(&game), 0);
+        assert!(tick(&mut game).piece_spawned);
+        // Only one hold until the piece locks
+        let before = game.board;
+        assert!(!game.hold().piece_held);
+        assert_eq!(game.board, before);
+        assert_eq!(game.held_piece(), Some(PieceKind::L));
+    }
+
+    #[test]
+    fn hold_into_empty_slot_spawns_next_piece_on_all_boards() {
+        on_all_boards!(hold_into_empty_slot_spawns_next_piece);
+    }
+
+    fn hold_swaps_with_held_piece_after_lock<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::new();
+        game.spawn_piece(PieceKind::L);
+        game.hold();
+        game.spawn_piece(PieceKind::O);
+        assert!(!game.hold().piece_held);
+        game.hard_drop();
+        game.spawn_piece(PieceKind::I);
+        game.drop_piece();
+        game.rotate_piece();
+        assert!(game.hold().piece_held);
+        assert_eq!(game.held_piece(), Some(PieceKind::I));
+        let piece = game.falling_piece().unwrap();
+        assert_eq!(piece.kind, PieceKind::L);
+        assert_eq!(piece.rotation, Rotation::Spawn);
+        assert_eq!(
+            piece.loc,
+            PieceLocation {
+                row: 0,
+                col: spawn_col::<W>() as isize
+            }
+        );
+        assert_eq!(filled(&game), 4 + 3);
+    }
+
+    #[test]
+    fn hold_swaps_with_held_piece_after_lock_on_all_boards() {
+        on_all_boards!(hold_swaps_with_held_piece_after_lock);
+    }
+
+    fn queue_previews_spawn_order<const W: usize, const H: usize>() {
+        let mut game = GameState::<W, H>::with_rules(Rules {
+            preview: 3,
+            ..Rules::MICRO
+        });
+        assert!(game.next_pieces().is_empty());
+        for seed in 0..20 {
+            let expected = game.next_pieces().first().copied();
+            // Clear the falling piece so the step spawns the next one
+            game.erase_piece();
+            game.falling = None;
+            assert!(game.step(seed).piece_spawned);
+            if let Some(kind) = expected {
+                assert_eq!(game.falling_piece().unwrap().kind, kind);
+            }
+            assert_eq!(game.next_pieces().len(), 3);
+        }
+    }
+
+    #[test]
+    fn queue_previews_spawn_order_on_all_boards() {
+        on_all_boards!(queue_previews_spawn_order);
+    }
+
+    #[test]
+    fn queue_length_follows_rules() {
+        for (preview, len) in [(0, 0), (1, 1), (6, 6), (10, MAX_PREVIEW)] {
+            let mut game = GameState::<10, 20>::with_rules(Rules {
+                preview,
+                ..Rules::GUIDELINE
+            });
+            assert!(game.step(7).piece_spawned);
+            assert_eq!(game.next_pieces().len(), len);
+            assert!(game.falling_piece().is_some());
+        }
+    }
+
+    #[test]
+    fn hold_into_empty_slot_takes_next_queued_piece() {
+        let mut game = GameState::<10, 20>::with_rules(Rules::GUIDELINE);
+        game.step(3);
+        let current = game.falling_piece().unwrap().kind;
+        let next = game.next_pieces()[0];
+        let after = game.next_pieces()[1];
+        assert!(game.hold().piece_held);
+        assert_eq!(game.held_piece(), Some(current));
+        assert_eq!(game.falling_piece().unwrap().kind, next);
+        assert_eq!(game.next_pieces()[0], after);
+        // The queue is topped back up on the next step
+        assert_eq!(game.next_pieces().len(), 4);
+        game.step(4);
+        assert_eq!(game.next_pieces().len(), 5);
+    }
+
+    #[test]
+    fn srs_lines_clear_on_standard_board() {
+        let mut game = GameState::<10, 20>::with_rules(Rules::GUIDELINE);
+        game.board = Board::from_rows(&["###.######", "###.######", "###.######", "###.######"]);
+        game.spawn_piece(PieceKind::I);
+        game.rotate_piece();
+        // Filled column of the vertical I is the third in its box
+        game.move_left();
+        game.move_left();
+        let outcome = step_until_locked(&mut game);
+        assert_eq!(outcome.lines_cleared, 4);
+        assert_eq!(filled(&game), 0);
+    }
+}
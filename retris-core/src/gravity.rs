@@ -0,0 +1,93 @@
+//! How fast pieces fall at each level
+//!
+//! Gravity is kept as a table of milliseconds per row so it can be checked on
+//! the host and tuned without touching the game loop.
+//!
+//! Gravity reference: <https://tetris.wiki/Marathon>
+
+/// Milliseconds a piece takes to fall one row at each level
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GravityTable {
+    /// Milliseconds per row, starting from level 1. Levels past the end of the
+    /// table keep the last entry.
+    pub ms_per_row: &'static [u16],
+}
+
+impl GravityTable {
+    /// Guideline curve, `(0.8 - (level - 1) * 0.007) ^ (level - 1)` seconds per
+    /// row, rounded to the millisecond up to level 15
+    pub const GUIDELINE: GravityTable = GravityTable {
+        ms_per_row: &[
+            1000, 793, 618, 473, 355, 262, 190, 135, 94, 64, 43, 28, 18, 11, 7,
+        ],
+    };
+    /// Gentle curve for the 5x5 pixel display, where a piece only falls a few
+    /// rows and fast gravity leaves no time to react
+    pub const LED: GravityTable = GravityTable {
+        ms_per_row: &[1500, 1400, 1300, 1200, 1100, 1000, 900, 800, 700, 600, 500],
+    };
+    /// Time for a piece to fall one row
+    ///
+    /// # Arguments
+    /// * `level` - The current level, starting from 1
+    ///
+    /// # Returns
+    /// * The gravity interval in milliseconds
+    pub fn interval(&self, level: u32) -> u16 {
+        let index = (level.max(1) as usize - 1).min(self.ms_per_row.len() - 1);
+        self.ms_per_row[index]
+    }
+    /// Frames a piece takes to fall one row
+    ///
+    /// # Arguments
+    /// * `level` - The current level, starting from 1
+    /// * `fps` - Frames per second
+    ///
+    /// # Returns
+    /// * The gravity interval rounded to whole frames, at least one frame
+    pub fn frames_per_row(&self, level: u32, fps: u32) -> u32 {
+        let frames = (self.interval(level) as u32 * fps + 500) / 1000;
+        frames.max(1)
+    }
+}
+
+impl Default for GravityTable {
+    fn default() -> Self {
+        Self::LED
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guideline_matches_frames_at_60_fps() {
+        let frames: [u32; 16] =
+            core::array::from_fn(|i| GravityTable::GUIDELINE.frames_per_row(i as u32 + 1, 60));
+        assert_eq!(
+            frames,
+            [60, 48, 37, 28, 21, 16, 11, 8, 6, 4, 3, 2, 1, 1, 1, 1]
+        );
+    }
+
+    #[test]
+    fn led_curve_is_gentle() {
+        let table = GravityTable::LED;
+        // The first level matches the original fixed tick
+        assert_eq!(table.interval(1), 1500);
+        let frames: [u32; 12] = core::array::from_fn(|i| table.frames_per_row(i as u32 + 1, 10));
+        assert_eq!(frames, [15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 5]);
+    }
+
+    #[test]
+    fn curves_never_slow_down() {
+        for table in [GravityTable::GUIDELINE, GravityTable::LED] {
+            for pair in table.ms_per_row.windows(2) {
+                assert!(pair[1] < pair[0]);
+            }
+            assert_eq!(table.interval(0), table.interval(1));
+            assert_eq!(table.interval(1000), *table.ms_per_row.last().unwrap());
+        }
+    }
+}
@@ -0,0 +1,460 @@
+//! Debounced press and release events for the buttons and logo
+//!
+//! Raw pin levels bounce for a few milliseconds when a button is pressed or
+//! released. Each input gets a debouncer that only
+//! believes a new level once it has held steady, and turns the steady levels
+//! into events. Inputs come from any [`EdgeQueue`] of timestamped edges
+//! rather than from sampling the pins: the firmware's buttons and logo, or a
+//! [`crate::edges::Script`] of recorded pin traces on the host.
+//!
+//! Holding a movement button auto-repeats it: one move on the press, then
+//! after a delay (DAS, delayed auto shift) more moves at a steady rate (ARR,
+//! auto repeat rate), as set in an [`AutoRepeat`] profile.
+use crate::edges::{Edge, EdgeQueue};
+
+/// The inputs on the front of the micro:bit v2
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Input {
+    /// A button, left of the display
+    A,
+    /// B button, right of the display
+    B,
+    /// Capacitive touch logo above the display
+    Logo,
+}
+
+impl Input {
+    /// Every input, in the order [`Controls`] keeps them
+    pub const ALL: [Input; 3] = [Input::A, Input::B, Input::Logo];
+}
+
+/// Something that happened to a debounced input
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ButtonEvent {
+    /// The input went down
+    Pressed,
+    /// The input came back up
+    Released,
+    /// The input is still down, with milliseconds since it went down
+    Held(u32),
+}
+
+/// Turns the raw samples of one input into steady presses and releases
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Debouncer {
+    /// Milliseconds a new level has to hold before it is believed
+    settle_ms: u32,
+    /// Debounced level
+    pressed: bool,
+    /// When the debounced level started, from the first sample at that level
+    since: u32,
+    /// When the raw level first differed from the debounced level, if it does
+    bouncing: Option<u32>,
+}
+
+impl Debouncer {
+    /// Create a debouncer for a released input
+    ///
+    /// # Arguments
+    /// * `settle_ms` - Milliseconds a new level has to hold before it is believed
+    pub const fn new(settle_ms: u32) -> Self {
+        Self {
+            settle_ms,
+            pressed: false,
+            since: 0,
+            bouncing: None,
+        }
+    }
+    /// Check if the input is down
+    ///
+    /// # Returns
+    /// * The debounced level, true if pressed
+    pub const fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+    /// Latest raw level, which differs from the debounced level while it settles
+    ///
+    /// # Returns
+    /// * The raw level, true if pressed
+    pub const fn raw(&self) -> bool {
+        self.pressed != self.bouncing.is_some()
+    }
+    /// Feed a raw sample in
+    ///
+    /// # Arguments
+    /// * `now` - When the sample was taken, in milliseconds
+    /// * `level` - The raw level, true if pressed
+    ///
+    /// # Returns
+    /// * The event the sample caused, if any
+    pub fn update(&mut self, now: u32, level: bool) -> Option<ButtonEvent> {
+        if level == self.pressed {
+            self.bouncing = None;
+            return self
+                .pressed
+                .then(|| ButtonEvent::Held(now.wrapping_sub(self.since)));
+        }
+        let started = *self.bouncing.get_or_insert(now);
+        if now.wrapping_sub(started) < self.settle_ms {
+            return self
+                .pressed
+                .then(|| ButtonEvent::Held(now.wrapping_sub(self.since)));
+        }
+        self.pressed = level;
+        self.since = started;
+        self.bouncing = None;
+        Some(if level {
+            ButtonEvent::Pressed
+        } else {
+            ButtonEvent::Released
+        })
+    }
+}
+
+/// Debouncers for every input
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Controls {
+    /// One debouncer for each input, in the order of [`Input::ALL`]
+    debouncers: [Debouncer; 3],
+}
+
+impl Controls {
+    /// Create debouncers for released inputs
+    ///
+    /// # Arguments
+    /// * `settle_ms` - Milliseconds a new level has to hold before it is believed
+    pub const fn new(settle_ms: u32) -> Self {
+        Self {
+            debouncers: [Debouncer::new(settle_ms); 3],
+        }
+    }
+    /// Check if an input is down
+    ///
+    /// # Arguments
+    /// * `input` - The input to check
+    ///
+    /// # Returns
+    /// * The debounced level, true if pressed
+    pub const fn is_pressed(&self, input: Input) -> bool {
+        self.debouncers[input as usize].is_pressed()
+    }
+    /// Feed in an edge from an interrupt
+    ///
+    /// The level before the edge is settled up to the time of the edge first,
+    /// so a press that ends before the next sample still counts.
+    ///
+    /// # Arguments
+    /// * `edge` - The change of level
+    ///
+    /// # Returns
+    /// * The press or release of the level before the edge settling, then the
+    ///   one of the edge itself, so an edge that causes both loses neither
+    pub fn feed(&mut self, edge: Edge) -> [Option<ButtonEvent>; 2] {
+        let debouncer = &mut self.debouncers[edge.input as usize];
+        let before = debouncer.update(edge.at, debouncer.raw());
+        let after = debouncer.update(edge.at, edge.pressed);
+        [before, after].map(|event| event.filter(|event| !matches!(event, ButtonEvent::Held(_))))
+    }
+    /// Let time pass for inputs fed with edges
+    ///
+    /// # Arguments
+    /// * `now` - The current time in milliseconds
+    ///
+    /// # Returns
+    /// * The event for each input in the order of [`Input::ALL`], None for
+    ///   inputs without an event
+    pub fn settle(&mut self, now: u32) -> [Option<ButtonEvent>; 3] {
+        self.debouncers
+            .each_mut()
+            .map(|debouncer| debouncer.update(now, debouncer.raw()))
+    }
+    /// Drain the edges of a source that happened by now, then let the levels
+    /// settle up to now
+    ///
+    /// # Arguments
+    /// * `edges` - Where the edges of the inputs arrive
+    /// * `now` - The current time in milliseconds
+    /// * `on_event` - Called with each event and its input, oldest first
+    pub fn poll<Q: EdgeQueue>(
+        &mut self,
+        edges: &mut Q,
+        now: u32,
+        mut on_event: impl FnMut(Input, ButtonEvent),
+    ) {
+        // Edges since the last time, so even quick taps count
+        while let Some(edge) = edges.pop_until(now) {
+            for event in self.feed(edge).into_iter().flatten() {
+                on_event(edge.input, event);
+            }
+        }
+        for (input, event) in Input::ALL.into_iter().zip(self.settle(now)) {
+            if let Some(event) = event {
+                on_event(input, event);
+            }
+        }
+    }
+}
+
+/// Auto-repeat timings for held movement buttons
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AutoRepeat {
+    /// Milliseconds from the press to the first repeat
+    pub delay_ms: u32,
+    /// Milliseconds between repeats after the first, at least 1
+    pub rate_ms: u32,
+}
+
+impl AutoRepeat {
+    /// Guideline handling, 10 frames of delay and 2 frames between repeats at
+    /// 60 frames per second
+    pub const GUIDELINE: AutoRepeat = AutoRepeat {
+        delay_ms: 167,
+        rate_ms: 33,
+    };
+    /// Slower handling for the 5x5 board, where a piece crosses the whole
+    /// board in four moves
+    pub const LED: AutoRepeat = AutoRepeat {
+        delay_ms: 300,
+        rate_ms: 150,
+    };
+    /// Number of repeats after a button has been held for a while
+    ///
+    /// # Arguments
+    /// * `held_ms` - Milliseconds since the button went down
+    ///
+    /// # Returns
+    /// * How many repeats are due, not counting the move on the press
+    pub const fn repeats(&self, held_ms: u32) -> u32 {
+        if held_ms < self.delay_ms {
+            return 0;
+        }
+        let rate = if self.rate_ms == 0 { 1 } else { self.rate_ms };
+        1 + (held_ms - self.delay_ms) / rate
+    }
+}
+
+impl Default for AutoRepeat {
+    fn default() -> Self {
+        Self::LED
+    }
+}
+
+/// Turns the events of one movement button into moves
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AutoShift {
+    /// Repeats already made for the current press
+    repeated: u32,
+}
+
+impl AutoShift {
+    /// Create an auto shift for a released button
+    pub const fn new() -> Self {
+        Self { repeated: 0 }
+    }
+    /// Count the moves an event of the button is worth
+    ///
+    /// # Arguments
+    /// * `event` - The latest event of the button, if any. Pass every event,
+    ///   since a press or release resets the repeats
+    /// * `repeat` - The auto-repeat timings
+    ///
+    /// # Returns
+    /// * Number of moves to make now
+    pub fn update(&mut self, event: Option<ButtonEvent>, repeat: &AutoRepeat) -> u32 {
+        match event {
+            Some(ButtonEvent::Pressed) => {
+                self.repeated = 0;
+                1
+            }
+            Some(ButtonEvent::Held(held_ms)) => {
+                let due = repeat.repeats(held_ms);
+                let moves = due.saturating_sub(self.repeated);
+                self.repeated = self.repeated.max(due);
+                moves
+            }
+            Some(ButtonEvent::Released) => {
+                self.repeated = 0;
+                0
+            }
+            None => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edges::Script;
+
+    /// Poll a script every millisecond and collect the presses and releases
+    fn events(
+        steps: &[(u32, Input, bool)],
+        settle_ms: u32,
+        until: u32,
+    ) -> [Option<(u32, ButtonEvent)>; 8] {
+        let mut script = Script::new(steps);
+        let mut controls = Controls::new(settle_ms);
+        let mut events = [None; 8];
+        let mut count = 0;
+        for now in 0..=until {
+            controls.poll(&mut script, now, |_, event| {
+                if !matches!(event, ButtonEvent::Held(_)) {
+                    events[count] = Some((now, event));
+                    count += 1;
+                }
+            });
+        }
+        events
+    }
+
+    #[test]
+    fn bouncy_press_is_one_event() {
+        use Input::A;
+        let trace = [
+            (10, A, true),
+            (11, A, false),
+            (12, A, true),
+            (13, A, false),
+            (14, A, true),
+            (60, A, false),
+            (61, A, true),
+            (62, A, false),
+        ];
+        let events = events(&trace, 5, 100);
+        assert_eq!(events[0], Some((19, ButtonEvent::Pressed)));
+        assert_eq!(events[1], Some((67, ButtonEvent::Released)));
+        assert_eq!(events[2], None);
+    }
+
+    #[test]
+    fn short_glitches_are_ignored() {
+        use Input::Logo;
+        let trace = [
+            (10, Logo, true),
+            (12, Logo, false),
+            (40, Logo, true),
+            (43, Logo, false),
+        ];
+        assert_eq!(events(&trace, 5, 100), [None; 8]);
+    }
+
+    #[test]
+    fn held_counts_from_the_first_sample_down() {
+        let mut debouncer = Debouncer::new(5);
+        assert_eq!(debouncer.update(100, true), None);
+        assert_eq!(debouncer.update(105, true), Some(ButtonEvent::Pressed));
+        assert!(debouncer.is_pressed());
+        assert_eq!(debouncer.update(150, true), Some(ButtonEvent::Held(50)));
+        // Still held while a release settles
+        assert_eq!(debouncer.update(160, false), Some(ButtonEvent::Held(60)));
+        assert_eq!(debouncer.update(165, false), Some(ButtonEvent::Released));
+        assert_eq!(debouncer.update(200, false), None);
+    }
+
+    #[test]
+    fn zero_settle_believes_every_edge() {
+        use Input::B;
+        let trace = [(5, B, true), (25, B, false)];
+        let events = events(&trace, 0, 30);
+        assert_eq!(events[0], Some((5, ButtonEvent::Pressed)));
+        assert_eq!(events[1], Some((25, ButtonEvent::Released)));
+        assert_eq!(events[2], None);
+    }
+
+    #[test]
+    fn zero_settle_keeps_back_to_back_edges() {
+        use Input::A;
+        // A tap and a second press, all inside one millisecond of each other
+        let trace = [(10, A, true), (10, A, false), (11, A, true)];
+        let events = events(&trace, 0, 20);
+        assert_eq!(events[0], Some((10, ButtonEvent::Pressed)));
+        assert_eq!(events[1], Some((10, ButtonEvent::Released)));
+        assert_eq!(events[2], Some((11, ButtonEvent::Pressed)));
+        assert_eq!(events[3], None);
+        // Polled late, every edge still counts in order
+        let mut script = Script::new(&trace);
+        let mut controls = Controls::new(0);
+        let mut seen = [None; 4];
+        let mut count = 0;
+        controls.poll(&mut script, 50, |_, event| {
+            seen[count] = Some(event);
+            count += 1;
+        });
+        assert_eq!(
+            seen,
+            [
+                Some(ButtonEvent::Pressed),
+                Some(ButtonEvent::Released),
+                Some(ButtonEvent::Pressed),
+                Some(ButtonEvent::Held(39))
+            ]
+        );
+    }
+
+    #[test]
+    fn held_button_moves_after_delay_then_at_rate() {
+        let repeat = AutoRepeat {
+            delay_ms: 100,
+            rate_ms: 30,
+        };
+        // Hold B from 20 ms to 250 ms, polled every 10 ms like the firmware
+        let mut script = Script::new(&[(20, Input::B, true), (250, Input::B, false)]);
+        let mut controls = Controls::new(10);
+        let mut shift = AutoShift::new();
+        let mut moves = [0; 8];
+        let mut count = 0;
+        for now in (0..400).step_by(10) {
+            controls.poll(&mut script, now, |input, event| {
+                if input == Input::B {
+                    for _ in 0..shift.update(Some(event), &repeat) {
+                        moves[count] = now;
+                        count += 1;
+                    }
+                }
+            });
+        }
+        // The press counts once debounced, then the delay and rate run from
+        // the edge down
+        assert_eq!(&moves[..count], &[30, 120, 150, 180, 210, 240]);
+    }
+
+    #[test]
+    fn late_samples_catch_up_on_repeats() {
+        let repeat = AutoRepeat::GUIDELINE;
+        let mut shift = AutoShift::new();
+        assert_eq!(shift.update(Some(ButtonEvent::Pressed), &repeat), 1);
+        assert_eq!(shift.update(Some(ButtonEvent::Held(100)), &repeat), 0);
+        assert_eq!(shift.update(Some(ButtonEvent::Held(167 + 66)), &repeat), 3);
+        assert_eq!(shift.update(Some(ButtonEvent::Held(167 + 70)), &repeat), 0);
+        assert_eq!(shift.update(Some(ButtonEvent::Released), &repeat), 0);
+        assert_eq!(shift.update(Some(ButtonEvent::Pressed), &repeat), 1);
+    }
+
+    #[test]
+    fn edges_between_samples_still_press() {
+        let mut controls = Controls::new(10);
+        let edge = |at, pressed| Edge {
+            at,
+            input: Input::Logo,
+            pressed,
+        };
+        // A bounce, then a 40 ms tap, all between two samples
+        assert_eq!(controls.feed(edge(100, true)), [None; 2]);
+        assert_eq!(controls.feed(edge(102, false)), [None; 2]);
+        assert_eq!(controls.feed(edge(105, true)), [None; 2]);
+        assert_eq!(
+            controls.feed(edge(145, false)),
+            [Some(ButtonEvent::Pressed), None]
+        );
+        assert!(controls.is_pressed(Input::Logo));
+        assert_eq!(
+            controls.settle(150),
+            [None, None, Some(ButtonEvent::Held(45))]
+        );
+        assert_eq!(
+            controls.settle(160),
+            [None, None, Some(ButtonEvent::Released)]
+        );
+        assert_eq!(controls.settle(170), [None; 3]);
+    }
+}
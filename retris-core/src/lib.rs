@@ -0,0 +1,44 @@
+//! Hardware-independent rules for R(ust) T(etris) on the micro:bit v2
+//!
+//! Holds the game state, tetrominos, and random number helpers so they can
+//! be built and tested on the host with `cargo test`, while the firmware
+//! crate only deals with the board's inputs, display, and speaker.
+#![no_std]
+
+/// Module for the playfield dimensions
+pub mod board;
+/// Module for the control schemes that turn input into actions
+pub mod controller;
+/// Module for timestamped pin edges from interrupts
+pub mod edges;
+/// Module for the game logic
+pub mod game;
+/// Module for how fast pieces fall at each level
+pub mod gravity;
+/// Module for debounced button and logo events
+pub mod input;
+/// Module for the queue of upcoming pieces
+pub mod queue;
+/// Module for random number helpers
+pub mod rand;
+/// Module for the randomizers that pick the next piece
+pub mod randomizer;
+/// Module for rendering the board as brightness values
+pub mod render;
+/// Module for the rules a game is played with
+pub mod rules;
+/// Module for deadlines in the main loop
+pub mod schedule;
+/// Module for scoring, lines, and levels
+pub mod scoring;
+/// Module for player settings
+pub mod settings;
+/// Module for beep patterns
+pub mod sound;
+/// Module for the tetromino shapes
+pub mod tetrominos;
+/// Module for moving pieces by tilting the board
+pub mod tilt;
+
+/// LED array proxy for rendering. Indexed as row and then column.
+pub type Raster = [[u8; 5]; 5];
@@ -0,0 +1,122 @@
+//! Upcoming pieces
+//!
+//! The game draws pieces from the randomizer ahead of time so renderers can
+//! preview what is coming next. The queue is a fixed array so it stays usable
+//! without an allocator.
+use crate::tetrominos::PieceKind;
+
+/// Most pieces the queue can hold ahead of the falling piece
+pub const MAX_PREVIEW: usize = 6;
+
+/// First in, first out queue of upcoming pieces
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PieceQueue {
+    /// Pieces in the order they will spawn, only the first `len` are used
+    kinds: [PieceKind; MAX_PREVIEW],
+    /// Number of pieces waiting
+    len: usize,
+}
+
+impl Default for PieceQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PieceQueue {
+    /// Create an empty queue
+    pub const fn new() -> Self {
+        Self {
+            kinds: [PieceKind::O; MAX_PREVIEW],
+            len: 0,
+        }
+    }
+    /// The waiting pieces
+    ///
+    /// # Returns
+    /// * The pieces in the order they will spawn, next piece first
+    pub fn as_slice(&self) -> &[PieceKind] {
+        &self.kinds[..self.len]
+    }
+    /// Number of waiting pieces
+    ///
+    /// # Returns
+    /// * How many pieces are in the queue
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+    /// Check if no pieces are waiting
+    ///
+    /// # Returns
+    /// * True if the queue is empty, false otherwise
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Add a piece to the back of the queue
+    ///
+    /// # Arguments
+    /// * `kind` - The piece to add
+    ///
+    /// # Returns
+    /// * True if the piece was added, false if the queue was already full
+    pub fn push(&mut self, kind: PieceKind) -> bool {
+        if self.len == MAX_PREVIEW {
+            return false;
+        }
+        self.kinds[self.len] = kind;
+        self.len += 1;
+        true
+    }
+    /// Take the piece from the front of the queue
+    ///
+    /// # Returns
+    /// * The next piece, or None if the queue is empty
+    pub fn pop(&mut self) -> Option<PieceKind> {
+        if self.len == 0 {
+            return None;
+        }
+        let kind = self.kinds[0];
+        self.kinds.copy_within(1..self.len, 0);
+        self.len -= 1;
+        Some(kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pieces_come_out_in_order() {
+        let mut queue = PieceQueue::new();
+        assert!(queue.is_empty());
+        for kind in [PieceKind::T, PieceKind::I, PieceKind::Z] {
+            assert!(queue.push(kind));
+        }
+        assert_eq!(
+            queue.as_slice(),
+            &[PieceKind::T, PieceKind::I, PieceKind::Z]
+        );
+        assert_eq!(queue.pop(), Some(PieceKind::T));
+        assert!(queue.push(PieceKind::L));
+        assert_eq!(
+            queue.as_slice(),
+            &[PieceKind::I, PieceKind::Z, PieceKind::L]
+        );
+        assert_eq!(queue.pop(), Some(PieceKind::I));
+        assert_eq!(queue.pop(), Some(PieceKind::Z));
+        assert_eq!(queue.pop(), Some(PieceKind::L));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn full_queue_refuses_pieces() {
+        let mut queue = PieceQueue::new();
+        for _ in 0..MAX_PREVIEW {
+            assert!(queue.push(PieceKind::S));
+        }
+        assert!(!queue.push(PieceKind::J));
+        assert_eq!(queue.len(), MAX_PREVIEW);
+        assert!(!queue.as_slice().contains(&PieceKind::J));
+    }
+}
@@ -0,0 +1,393 @@
+//! Randomizers that pick the next piece
+//!
+//! Each randomizer implements [`Randomizer`] and keeps whatever it remembers
+//! between pieces, such as the rest of a bag or the last few pieces dealt. The
+//! random number generator is passed in so the game can keep seeding each step.
+//!
+//! Randomizer reference: <https://tetris.wiki/Random_Generator>
+use nanorand::Pcg64;
+
+use crate::rand::random_range;
+use crate::tetrominos::{get_random_tetromino, PieceKind, PieceSet};
+
+/// Picks the pieces that are dealt to the player
+pub trait Randomizer {
+    /// Draw the next piece
+    ///
+    /// # Arguments
+    /// * `rng` - A mutable reference to a Pcg64 random number generator.
+    ///
+    /// # Returns
+    /// * The kind of the next piece
+    fn next_piece(&mut self, rng: &mut Pcg64) -> PieceKind;
+}
+
+/// Pick a kind from a piece set with equal odds
+///
+/// # Arguments
+/// * `set` - The piece set to pick from
+/// * `rng` - A mutable reference to a Pcg64 random number generator.
+///
+/// # Returns
+/// * A kind from the set
+fn uniform_kind(set: PieceSet, rng: &mut Pcg64) -> PieceKind {
+    let kinds = set.kinds();
+    kinds[random_range(rng, 0, kinds.len() as u8) as usize]
+}
+
+/// The original hand-weighted table, four in ten pieces are `L`
+///
+/// Only deals the kinds of the micro set.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Weighted;
+
+impl Randomizer for Weighted {
+    fn next_piece(&mut self, rng: &mut Pcg64) -> PieceKind {
+        get_random_tetromino(rng)
+    }
+}
+
+/// Every kind in the set has the same chance each piece
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Uniform {
+    /// Piece set to deal from
+    set: PieceSet,
+}
+
+impl Uniform {
+    /// Create a uniform randomizer
+    ///
+    /// # Arguments
+    /// * `set` - The piece set to deal from
+    pub const fn new(set: PieceSet) -> Self {
+        Self { set }
+    }
+}
+
+impl Randomizer for Uniform {
+    fn next_piece(&mut self, rng: &mut Pcg64) -> PieceKind {
+        uniform_kind(self.set, rng)
+    }
+}
+
+/// Most kinds a bag can hold, the size of the SRS set
+const MAX_BAG: usize = 7;
+
+/// Deals every kind in the set once, in a shuffled order, before refilling
+///
+/// With the SRS set this is the guideline 7-bag. The bag is sized to the set,
+/// so the micro set deals from a 5-bag.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bag {
+    /// Piece set to deal from
+    set: PieceSet,
+    /// Shuffled kinds, dealt from the back
+    bag: [PieceKind; MAX_BAG],
+    /// Kinds left in the bag
+    remaining: usize,
+}
+
+impl Bag {
+    /// Create an empty bag, it is filled on the first draw
+    ///
+    /// # Arguments
+    /// * `set` - The piece set to deal from
+    pub const fn new(set: PieceSet) -> Self {
+        Self {
+            set,
+            bag: [PieceKind::O; MAX_BAG],
+            remaining: 0,
+        }
+    }
+    /// Put every kind back in the bag and shuffle it
+    ///
+    /// # Arguments
+    /// * `rng` - A mutable reference to a Pcg64 random number generator.
+    fn refill(&mut self, rng: &mut Pcg64) {
+        let kinds = self.set.kinds();
+        self.bag[..kinds.len()].copy_from_slice(kinds);
+        // Fisher-Yates shuffle
+        for i in (1..kinds.len()).rev() {
+            let j = random_range(rng, 0, i as u8 + 1) as usize;
+            self.bag.swap(i, j);
+        }
+        self.remaining = kinds.len();
+    }
+}
+
+impl Randomizer for Bag {
+    fn next_piece(&mut self, rng: &mut Pcg64) -> PieceKind {
+        if self.remaining == 0 {
+            self.refill(rng);
+        }
+        self.remaining -= 1;
+        self.bag[self.remaining]
+    }
+}
+
+/// Number of recent pieces the history randomizer avoids
+const HISTORY_LEN: usize = 4;
+
+/// TGM-style randomizer that rerolls pieces it dealt recently
+///
+/// Keeps the last four pieces and rerolls up to `rolls` times when a roll is
+/// one of them. The history starts full of S and Z pieces, and the first piece
+/// is never an S, Z, or O, so a game never opens with an overhang.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct History {
+    /// Piece set to deal from
+    set: PieceSet,
+    /// Number of times to roll before accepting a recent piece
+    rolls: u8,
+    /// Most recent piece last
+    history: [PieceKind; HISTORY_LEN],
+    /// Set once the first piece has been dealt
+    started: bool,
+}
+
+impl History {
+    /// Create a history randomizer with the TGM2 number of rolls
+    ///
+    /// # Arguments
+    /// * `set` - The piece set to deal from
+    pub const fn new(set: PieceSet) -> Self {
+        Self::with_rolls(set, 6)
+    }
+    /// Create a history randomizer
+    ///
+    /// # Arguments
+    /// * `set` - The piece set to deal from
+    /// * `rolls` - How many times to roll before accepting a recent piece,
+    ///   at least one roll is always made
+    pub const fn with_rolls(set: PieceSet, rolls: u8) -> Self {
+        Self {
+            set,
+            rolls,
+            history: [PieceKind::Z, PieceKind::S, PieceKind::Z, PieceKind::S],
+            started: false,
+        }
+    }
+    /// Pick the first piece of a game, avoiding the pieces that leave overhangs
+    ///
+    /// # Arguments
+    /// * `rng` - A mutable reference to a Pcg64 random number generator.
+    ///
+    /// # Returns
+    /// * A kind from the set other than S, Z, or O
+    fn first_piece(&self, rng: &mut Pcg64) -> PieceKind {
+        let mut kinds = [PieceKind::O; MAX_BAG];
+        let mut len = 0;
+        for &kind in self.set.kinds() {
+            if !matches!(kind, PieceKind::S | PieceKind::Z | PieceKind::O) {
+                kinds[len] = kind;
+                len += 1;
+            }
+        }
+        kinds[random_range(rng, 0, len as u8) as usize]
+    }
+}
+
+impl Randomizer for History {
+    fn next_piece(&mut self, rng: &mut Pcg64) -> PieceKind {
+        let kind = if self.started {
+            let mut kind = uniform_kind(self.set, rng);
+            for _ in 1..self.rolls {
+                if !self.history.contains(&kind) {
+                    break;
+                }
+                kind = uniform_kind(self.set, rng);
+            }
+            kind
+        } else {
+            self.started = true;
+            self.first_piece(rng)
+        };
+        self.history.rotate_left(1);
+        self.history[HISTORY_LEN - 1] = kind;
+        kind
+    }
+}
+
+/// Which randomizer a game deals pieces with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RandomizerKind {
+    /// See [`Weighted`]
+    Weighted,
+    /// See [`Uniform`]
+    Uniform,
+    /// See [`Bag`]
+    #[default]
+    Bag,
+    /// See [`History`]
+    History,
+}
+
+/// One of the randomizers, picked when a game starts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PieceRandomizer {
+    /// See [`Weighted`]
+    Weighted(Weighted),
+    /// See [`Uniform`]
+    Uniform(Uniform),
+    /// See [`Bag`]
+    Bag(Bag),
+    /// See [`History`]
+    History(History),
+}
+
+impl PieceRandomizer {
+    /// Create the chosen randomizer
+    ///
+    /// # Arguments
+    /// * `kind` - Which randomizer to create
+    /// * `set` - The piece set to deal from
+    pub const fn new(kind: RandomizerKind, set: PieceSet) -> Self {
+        match kind {
+            RandomizerKind::Weighted => Self::Weighted(Weighted),
+            RandomizerKind::Uniform => Self::Uniform(Uniform::new(set)),
+            RandomizerKind::Bag => Self::Bag(Bag::new(set)),
+            RandomizerKind::History => Self::History(History::new(set)),
+        }
+    }
+}
+
+impl Randomizer for PieceRandomizer {
+    fn next_piece(&mut self, rng: &mut Pcg64) -> PieceKind {
+        match self {
+            Self::Weighted(randomizer) => randomizer.next_piece(rng),
+            Self::Uniform(randomizer) => randomizer.next_piece(rng),
+            Self::Bag(randomizer) => randomizer.next_piece(rng),
+            Self::History(randomizer) => randomizer.next_piece(rng),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Number of pieces drawn for each distribution test
+    const DRAWS: usize = 7000;
+
+    /// Count how often each kind of the set is drawn
+    fn counts(set: PieceSet, randomizer: &mut impl Randomizer) -> [usize; MAX_BAG] {
+        let mut rng = Pcg64::new_seed(1337);
+        let mut counts = [0; MAX_BAG];
+        for _ in 0..DRAWS {
+            let kind = randomizer.next_piece(&mut rng);
+            let index = set.kinds().iter().position(|&k| k == kind).unwrap();
+            counts[index] += 1;
+        }
+        counts
+    }
+
+    /// Check each count is within `tolerance` of its expected share of the draws
+    fn assert_shares(counts: &[usize], shares: &[f32], tolerance: f32) {
+        for (&count, &share) in counts.iter().zip(shares) {
+            let expected = share * DRAWS as f32;
+            let error = (count as f32 - expected).abs() / expected;
+            assert!(error < tolerance, "{counts:?} strays from {shares:?}");
+        }
+    }
+
+    #[test]
+    fn weighted_favours_l() {
+        let set = PieceSet::Micro;
+        // I, O, T, S, L
+        let counts = counts(set, &mut Weighted);
+        assert_shares(&counts, &[0.1, 0.1, 0.2, 0.2, 0.4], 0.1);
+    }
+
+    #[test]
+    fn uniform_deals_evenly() {
+        for set in [PieceSet::Micro, PieceSet::Srs] {
+            let len = set.kinds().len();
+            let counts = counts(set, &mut Uniform::new(set));
+            assert_shares(&counts[..len], &[1.0 / len as f32; MAX_BAG], 0.1);
+        }
+    }
+
+    #[test]
+    fn bag_deals_each_kind_once_per_bag() {
+        let mut rng = Pcg64::new_seed(7);
+        for set in [PieceSet::Micro, PieceSet::Srs] {
+            let kinds = set.kinds();
+            let mut bag = Bag::new(set);
+            for _ in 0..100 {
+                let mut dealt = [PieceKind::O; MAX_BAG];
+                for slot in &mut dealt[..kinds.len()] {
+                    *slot = bag.next_piece(&mut rng);
+                }
+                for kind in kinds {
+                    let times = dealt[..kinds.len()].iter().filter(|&k| k == kind);
+                    assert_eq!(times.count(), 1, "{dealt:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bag_shuffles_between_bags() {
+        let mut rng = Pcg64::new_seed(7);
+        let mut bag = Bag::new(PieceSet::Srs);
+        let first: [PieceKind; 7] = core::array::from_fn(|_| bag.next_piece(&mut rng));
+        let orders_differ = (0..10).any(|_| {
+            let next: [PieceKind; 7] = core::array::from_fn(|_| bag.next_piece(&mut rng));
+            next != first
+        });
+        assert!(orders_differ);
+    }
+
+    #[test]
+    fn history_deals_evenly_and_rarely_repeats() {
+        for set in [PieceSet::Micro, PieceSet::Srs] {
+            let len = set.kinds().len();
+            let counts = counts(set, &mut History::new(set));
+            assert_shares(&counts[..len], &[1.0 / len as f32; MAX_BAG], 0.15);
+        }
+        let mut rng = Pcg64::new_seed(99);
+        let mut history = History::new(PieceSet::Srs);
+        let mut last = history.next_piece(&mut rng);
+        let mut repeats = 0;
+        for _ in 0..DRAWS {
+            let kind = history.next_piece(&mut rng);
+            if kind == last {
+                repeats += 1;
+            }
+            last = kind;
+        }
+        // A uniform randomizer repeats one piece in seven
+        assert!(repeats < DRAWS / 50, "{repeats} repeats");
+    }
+
+    #[test]
+    fn history_never_opens_with_overhang() {
+        for seed in 0..100 {
+            for set in [PieceSet::Micro, PieceSet::Srs] {
+                let mut rng = Pcg64::new_seed(seed);
+                let first = History::new(set).next_piece(&mut rng);
+                assert!(!matches!(first, PieceKind::S | PieceKind::Z | PieceKind::O));
+            }
+        }
+    }
+
+    #[test]
+    fn chosen_randomizer_stays_in_set() {
+        let mut rng = Pcg64::new_seed(1337);
+        let kinds = [
+            RandomizerKind::Weighted,
+            RandomizerKind::Uniform,
+            RandomizerKind::Bag,
+            RandomizerKind::History,
+        ];
+        for kind in kinds {
+            for set in [PieceSet::Micro, PieceSet::Srs] {
+                let mut randomizer = PieceRandomizer::new(kind, set);
+                for _ in 0..100 {
+                    let piece = randomizer.next_piece(&mut rng);
+                    assert!(set.kinds().contains(&piece));
+                }
+            }
+        }
+    }
+}
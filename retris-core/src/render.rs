@@ -0,0 +1,292 @@
+//! Turn the playfield into brightness values
+//!
+//! Keeps how the board looks separate from the game rules, so changing a
+//! brightness never changes how pieces collide or rows clear.
+use crate::board::{Board, Cell};
+use crate::game::{FallingPiece, GameState};
+use crate::settings::Settings;
+use crate::tetrominos::Shape;
+
+/// Brightness used for each kind of cell, from 0 (off) to 9 (brightest)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Palette {
+    /// Brightness of empty cells
+    pub empty: u8,
+    /// Brightness of locked blocks
+    pub locked: u8,
+    /// Brightness of the falling piece
+    pub active: u8,
+    /// Brightness of the next piece previewed over empty cells
+    pub preview: u8,
+    /// Brightness of the ghost of the falling piece over empty cells
+    pub ghost: u8,
+}
+
+/// Palette for the built-in 5x5 pixel display on the micro:bit v2
+pub const LED_PALETTE: Palette = Palette {
+    empty: 0,
+    locked: 5,
+    active: 9,
+    preview: 2,
+    ghost: 1,
+};
+
+impl Default for Palette {
+    fn default() -> Self {
+        LED_PALETTE
+    }
+}
+
+impl Palette {
+    /// Look up the brightness of a cell
+    ///
+    /// # Arguments
+    /// * `cell` - The cell to light
+    ///
+    /// # Returns
+    /// * The brightness of the cell
+    pub const fn brightness(&self, cell: Cell) -> u8 {
+        match cell {
+            Cell::Empty => self.empty,
+            Cell::Locked(_) => self.locked,
+            Cell::Active => self.active,
+        }
+    }
+}
+
+/// Render a board into a grid of brightness values
+///
+/// # Arguments
+/// * `board` - The board to render
+/// * `palette` - The brightness of each kind of cell
+///
+/// # Returns
+/// * Brightness values indexed as row and then column, a `Raster` for the 5x5 board
+pub fn render<const W: usize, const H: usize>(
+    board: &Board<W, H>,
+    palette: &Palette,
+) -> [[u8; W]; H] {
+    let mut frame = [[0; W]; H];
+    for (frame_row, row) in frame.iter_mut().zip(board.rows()) {
+        for (pixel, &cell) in frame_row.iter_mut().zip(row) {
+            *pixel = palette.brightness(cell);
+        }
+    }
+    frame
+}
+
+/// Render a lone piece shape in the middle of an otherwise empty frame
+///
+/// Used to show a piece off the board, such as the held piece.
+///
+/// # Arguments
+/// * `shape` - The piece shape to draw
+/// * `palette` - The brightness of each kind of cell
+///
+/// # Returns
+/// * Brightness values indexed as row and then column, a `Raster` for a 5x5 frame
+pub fn render_shape<const W: usize, const H: usize>(
+    shape: Shape,
+    palette: &Palette,
+) -> [[u8; W]; H] {
+    let mut frame = [[palette.empty; W]; H];
+    let top = H.saturating_sub(shape.size) / 2;
+    let left = W.saturating_sub(shape.size) / 2;
+    for (row, col) in shape.filled() {
+        if let Some(pixel) = frame.get_mut(top + row).and_then(|r| r.get_mut(left + col)) {
+            *pixel = palette.active;
+        }
+    }
+    frame
+}
+
+/// Light the empty cells of a frame under the given board cells
+///
+/// # Arguments
+/// * `frame` - The rendered board to draw on
+/// * `board` - The board the frame was rendered from
+/// * `cells` - The row and column of each cell to light, cells off the board are skipped
+/// * `brightness` - The brightness to light them with
+fn draw_over_empty<const W: usize, const H: usize>(
+    frame: &mut [[u8; W]; H],
+    board: &Board<W, H>,
+    cells: impl Iterator<Item = (isize, isize)>,
+    brightness: u8,
+) {
+    for (row, col) in cells {
+        let (Ok(row), Ok(col)) = (usize::try_from(row), usize::try_from(col)) else {
+            continue;
+        };
+        if row < H && col < W && board[row][col] == Cell::Empty {
+            frame[row][col] = brightness;
+        }
+    }
+}
+
+/// Draw the next piece where it will spawn
+///
+/// The preview is only drawn over empty cells, so it never hides the board.
+///
+/// # Arguments
+/// * `frame` - The rendered board to draw on
+/// * `board` - The board the frame was rendered from
+/// * `shape` - The shape of the next piece
+/// * `col` - The column of the left edge of the next piece's bounding box
+/// * `palette` - The brightness of each kind of cell
+pub fn draw_preview<const W: usize, const H: usize>(
+    frame: &mut [[u8; W]; H],
+    board: &Board<W, H>,
+    shape: Shape,
+    col: usize,
+    palette: &Palette,
+) {
+    let cells = shape
+        .filled()
+        .map(|(row, shape_col)| (row as isize, (col + shape_col) as isize));
+    draw_over_empty(frame, board, cells, palette.preview);
+}
+
+/// Draw the ghost of the falling piece where it would land
+///
+/// Like the preview, the ghost is only drawn over empty cells, so the falling
+/// piece shows over its ghost once it has landed.
+///
+/// # Arguments
+/// * `frame` - The rendered board to draw on
+/// * `board` - The board the frame was rendered from
+/// * `ghost` - The falling piece at its landing location
+/// * `palette` - The brightness of each kind of cell
+pub fn draw_ghost<const W: usize, const H: usize>(
+    frame: &mut [[u8; W]; H],
+    board: &Board<W, H>,
+    ghost: &FallingPiece,
+    palette: &Palette,
+) {
+    draw_over_empty(frame, board, ghost.cells(), palette.ghost);
+}
+
+/// Render a game's board along with the ghost piece when it is turned on
+///
+/// # Arguments
+/// * `game` - The game to render
+/// * `settings` - The player settings, which turn the ghost on or off
+/// * `palette` - The brightness of each kind of cell
+///
+/// # Returns
+/// * Brightness values indexed as row and then column, a `Raster` for the 5x5 board
+pub fn render_game<const W: usize, const H: usize>(
+    game: &GameState<W, H>,
+    settings: &Settings,
+    palette: &Palette,
+) -> [[u8; W]; H] {
+    let mut frame = render(game.board(), palette);
+    if settings.ghost {
+        if let Some(ghost) = game.ghost_piece() {
+            draw_ghost(&mut frame, game.board(), &ghost, palette);
+        }
+    }
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tetrominos::{PieceKind, PieceSet};
+
+    #[test]
+    fn render_uses_palette_for_each_cell() {
+        let mut board = Board::<3, 2>::new();
+        board[0][1] = Cell::Active;
+        board[1][0] = Cell::Locked(PieceKind::L);
+        board[1][2] = Cell::Locked(PieceKind::O);
+        assert_eq!(render(&board, &LED_PALETTE), [[0, 9, 0], [5, 0, 5]]);
+        let dim = Palette {
+            empty: 1,
+            locked: 2,
+            active: 3,
+            preview: 4,
+            ghost: 5,
+        };
+        assert_eq!(render(&board, &dim), [[1, 3, 1], [2, 1, 2]]);
+    }
+
+    #[test]
+    fn render_shape_centers_piece() {
+        let square = PieceSet::Micro.shape(PieceKind::O);
+        let frame: [[u8; 5]; 5] = render_shape(square, &LED_PALETTE);
+        assert_eq!(
+            frame,
+            [
+                [0, 0, 0, 0, 0],
+                [0, 9, 9, 0, 0],
+                [0, 9, 9, 0, 0],
+                [0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0],
+            ]
+        );
+        let straight = PieceSet::Srs.shape(PieceKind::I);
+        let frame: [[u8; 4]; 3] = render_shape(straight, &LED_PALETTE);
+        assert_eq!(frame, [[0; 4], [9; 4], [0; 4]]);
+    }
+
+    #[test]
+    fn preview_only_covers_empty_cells() {
+        let mut board = Board::<5, 5>::new();
+        board[1][2] = Cell::Locked(PieceKind::O);
+        let square = PieceSet::Micro.shape(PieceKind::O);
+        let mut frame = render(&board, &LED_PALETTE);
+        draw_preview(&mut frame, &board, square, 2, &LED_PALETTE);
+        assert_eq!(
+            frame,
+            [
+                [0, 0, 2, 2, 0],
+                [0, 0, 5, 2, 0],
+                [0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0],
+            ]
+        );
+        let straight = PieceSet::Srs.shape(PieceKind::I);
+        let board = Board::<5, 2>::new();
+        let mut frame = render(&board, &LED_PALETTE);
+        draw_preview(&mut frame, &board, straight, 3, &LED_PALETTE);
+        assert_eq!(frame, [[0; 5], [0, 0, 0, 2, 2]]);
+    }
+
+    #[test]
+    fn ghost_is_drawn_at_landing_row_when_turned_on() {
+        let mut game = GameState::<5, 5>::new();
+        game.step(0);
+        let piece = *game.falling_piece().unwrap();
+        let ghost = game.ghost_piece().unwrap();
+        assert_eq!(ghost.loc.col, piece.loc.col);
+        assert_eq!(game.landing_row(), Some(ghost.loc.row));
+        let frame = render_game(&game, &Settings::default(), &LED_PALETTE);
+        for (row, col) in ghost.cells() {
+            assert_eq!(frame[row as usize][col as usize], LED_PALETTE.ghost);
+        }
+        for (row, col) in piece.cells() {
+            assert_eq!(frame[row as usize][col as usize], LED_PALETTE.active);
+        }
+        let off = Settings {
+            ghost: false,
+            ..Settings::default()
+        };
+        assert_eq!(
+            render_game(&game, &off, &LED_PALETTE),
+            render(game.board(), &LED_PALETTE)
+        );
+    }
+
+    #[test]
+    fn ghost_hides_under_landed_piece() {
+        let mut game = GameState::<5, 5>::new();
+        game.step(0);
+        while game.landing_row() != game.falling_piece().map(|piece| piece.loc.row) {
+            game.soft_drop();
+        }
+        assert!(game.falling_piece().is_some());
+        let frame = render_game(&game, &Settings::default(), &LED_PALETTE);
+        assert_eq!(frame, render(game.board(), &LED_PALETTE));
+    }
+}
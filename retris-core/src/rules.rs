@@ -0,0 +1,109 @@
+//! Rules a game is played with
+//!
+//! Collects the choices that differ between the 5x5 pixel display and a
+//! standard board, so the same game logic can run either.
+use crate::gravity::GravityTable;
+use crate::randomizer::RandomizerKind;
+use crate::scoring::ScoreTable;
+use crate::tetrominos::PieceSet;
+
+/// How blocks above a cleared row fall
+///
+/// Line clear reference: <https://tetris.wiki/Line_clear>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineClear {
+    /// Rows shift down whole, leaving overhangs in place
+    Naive,
+    /// Connected groups of blocks fall as units, then new full rows clear too
+    Sticky,
+    /// Every block falls on its own, then new full rows clear too
+    Cascade,
+}
+
+/// What resets the lock delay of a piece resting on the stack
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LockReset {
+    /// Only falling resets the delay
+    Step,
+    /// Falling, moving, and rotating all reset the delay
+    Move,
+}
+
+/// How long a piece can rest on the stack before it locks
+///
+/// Reaching a row lower than the piece has been before clears the reset count,
+/// so only stalling in place uses up the resets.
+///
+/// Lock delay reference: <https://tetris.wiki/Lock_delay>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LockDelay {
+    /// Milliseconds a piece rests before locking, 0 locks on contact
+    pub ms: u16,
+    /// What resets the delay
+    pub reset: LockReset,
+    /// Resets allowed before the piece locks as soon as the delay runs out
+    pub max_resets: u8,
+}
+
+/// Rules a game is played with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rules {
+    /// Set of pieces to spawn
+    pub piece_set: PieceSet,
+    /// Number of upcoming pieces to draw ahead of time, from 0 to
+    /// [`crate::queue::MAX_PREVIEW`]
+    pub preview: usize,
+    /// Randomizer that picks the next piece
+    pub randomizer: RandomizerKind,
+    /// Points for clears and drops, and lines per level
+    pub scoring: ScoreTable,
+    /// Milliseconds per row at each level
+    pub gravity: GravityTable,
+    /// Time a piece can rest on the stack before locking
+    pub lock_delay: LockDelay,
+    /// How blocks fall after a line clear
+    pub line_clear: LineClear,
+    /// Rows at the top of the board above the visible area, where pieces spawn.
+    /// A piece that locks entirely in these rows ends the game.
+    pub hidden_rows: usize,
+}
+
+impl Rules {
+    /// Rules for the built-in 5x5 pixel display on the micro:bit v2
+    pub const MICRO: Rules = Rules {
+        piece_set: PieceSet::Micro,
+        preview: 1,
+        randomizer: RandomizerKind::Bag,
+        scoring: ScoreTable::MICRO,
+        gravity: GravityTable::LED,
+        lock_delay: LockDelay {
+            ms: 500,
+            reset: LockReset::Step,
+            max_resets: 15,
+        },
+        line_clear: LineClear::Cascade,
+        hidden_rows: 0,
+    };
+    /// Rules for a standard board following the Tetris guideline, 10x22 with
+    /// the top two rows hidden
+    pub const GUIDELINE: Rules = Rules {
+        piece_set: PieceSet::Srs,
+        preview: 5,
+        randomizer: RandomizerKind::Bag,
+        scoring: ScoreTable::GUIDELINE,
+        gravity: GravityTable::GUIDELINE,
+        lock_delay: LockDelay {
+            ms: 500,
+            reset: LockReset::Move,
+            max_resets: 15,
+        },
+        line_clear: LineClear::Naive,
+        hidden_rows: 2,
+    };
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self::MICRO
+    }
+}
@@ -0,0 +1,242 @@
+//! Deadlines for the main loop
+//!
+//! The firmware reads a free-running millisecond clock and asks the scheduler
+//! which events are due, instead of blocking for a whole gravity tick. Times
+//! are `u32` milliseconds that wrap around, so deadlines are compared by their
+//! wrapping distance and keep working after the clock rolls over.
+use core::mem::discriminant;
+
+/// Check if a deadline has been reached
+///
+/// # Arguments
+/// * `now` - The current time in milliseconds
+/// * `at` - The deadline in milliseconds
+///
+/// # Returns
+/// * True if `now` is at or past `at`, allowing for the clock wrapping around
+pub const fn reached(now: u32, at: u32) -> bool {
+    (now.wrapping_sub(at) as i32) >= 0
+}
+
+/// Convert a count of clock ticks to milliseconds
+///
+/// # Arguments
+/// * `ticks` - Ticks counted since the clock started
+/// * `hz` - Ticks per second
+///
+/// # Returns
+/// * Milliseconds since the clock started, wrapping around at `u32::MAX`
+pub const fn millis(ticks: u64, hz: u32) -> u32 {
+    let hz = hz as u64;
+    // Split off whole seconds so the multiply cannot overflow
+    (ticks / hz * 1000 + ticks % hz * 1000 / hz) as u32
+}
+
+/// Convert milliseconds to a count of clock ticks
+///
+/// # Arguments
+/// * `ms` - Milliseconds
+/// * `hz` - Ticks per second
+///
+/// # Returns
+/// * The ticks that take at least `ms` milliseconds, rounded up
+pub const fn ticks(ms: u32, hz: u32) -> u64 {
+    (ms as u64 * hz as u64).div_ceil(1000)
+}
+
+/// Pending events, each due at a point in time
+///
+/// Only one event of each enum variant is pending at once, so scheduling a
+/// variant again moves its deadline instead of queueing a second copy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Scheduler<E, const N: usize> {
+    /// Deadline and event in each slot that is in use
+    slots: [Option<(u32, E)>; N],
+}
+
+impl<E: Copy, const N: usize> Default for Scheduler<E, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Copy, const N: usize> Scheduler<E, N> {
+    /// Create a scheduler with nothing pending
+    pub fn new() -> Self {
+        Self { slots: [None; N] }
+    }
+    /// Schedule an event, replacing a pending event of the same variant
+    ///
+    /// # Arguments
+    /// * `at` - When the event is due in milliseconds
+    /// * `event` - The event
+    ///
+    /// # Returns
+    /// * True if the event was scheduled, false if every slot was in use
+    pub fn schedule(&mut self, at: u32, event: E) -> bool {
+        let slot = self
+            .slots
+            .iter()
+            .position(|slot| slot.is_some_and(|(_, e)| discriminant(&e) == discriminant(&event)))
+            .or_else(|| self.slots.iter().position(Option::is_none));
+        match slot {
+            Some(index) => {
+                self.slots[index] = Some((at, event));
+                true
+            }
+            None => false,
+        }
+    }
+    /// Schedule an event some time from now
+    ///
+    /// # Arguments
+    /// * `now` - The current time in milliseconds
+    /// * `delay` - Milliseconds until the event is due
+    /// * `event` - The event
+    ///
+    /// # Returns
+    /// * True if the event was scheduled, false if every slot was in use
+    pub fn schedule_in(&mut self, now: u32, delay: u32, event: E) -> bool {
+        self.schedule(now.wrapping_add(delay), event)
+    }
+    /// Drop the pending event of the same variant, if any
+    ///
+    /// # Arguments
+    /// * `event` - An event of the variant to cancel
+    pub fn cancel(&mut self, event: E) {
+        for slot in self.slots.iter_mut() {
+            if slot.is_some_and(|(_, e)| discriminant(&e) == discriminant(&event)) {
+                *slot = None;
+            }
+        }
+    }
+    /// Check if an event of the same variant is pending
+    ///
+    /// # Arguments
+    /// * `event` - An event of the variant to look for
+    ///
+    /// # Returns
+    /// * The deadline of the pending event, or None if there is none
+    pub fn deadline(&self, event: E) -> Option<u32> {
+        self.slots
+            .iter()
+            .flatten()
+            .find_map(|&(at, e)| (discriminant(&e) == discriminant(&event)).then_some(at))
+    }
+    /// Earliest pending deadline
+    ///
+    /// # Arguments
+    /// * `now` - The current time in milliseconds
+    ///
+    /// # Returns
+    /// * The deadline closest to `now`, or None if nothing is pending
+    pub fn next_deadline(&self, now: u32) -> Option<u32> {
+        let index = self.earliest(now)?;
+        self.slots[index].map(|(at, _)| at)
+    }
+    /// Take the earliest event that is due
+    ///
+    /// # Arguments
+    /// * `now` - The current time in milliseconds
+    ///
+    /// # Returns
+    /// * The event, or None if nothing is due yet
+    pub fn pop_due(&mut self, now: u32) -> Option<E> {
+        let index = self.earliest(now)?;
+        let (at, event) = self.slots[index]?;
+        if !reached(now, at) {
+            return None;
+        }
+        self.slots[index] = None;
+        Some(event)
+    }
+    /// Find the slot with the earliest deadline
+    ///
+    /// # Arguments
+    /// * `now` - The current time, which deadlines are measured from
+    ///
+    /// # Returns
+    /// * The index of the slot, or None if nothing is pending
+    fn earliest(&self, now: u32) -> Option<usize> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| slot.map(|(at, _)| (index, at.wrapping_sub(now) as i32)))
+            .min_by_key(|&(_, until)| until)
+            .map(|(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    enum Event {
+        Input,
+        Gravity,
+        Beep(u8),
+    }
+
+    #[test]
+    fn rtc_ticks_convert_to_millis() {
+        assert_eq!(millis(0, 32_768), 0);
+        assert_eq!(millis(32_768, 32_768), 1000);
+        assert_eq!(millis(32_768 + 16_384, 32_768), 1500);
+        // A 24-bit counter that has rolled over many times
+        let ticks = (1_000_000_u64 << 24) | 0xFF_FFFF;
+        assert_eq!(millis(ticks, 32_768), (ticks * 1000 / 32_768) as u32);
+    }
+
+    #[test]
+    fn millis_convert_to_enough_ticks() {
+        assert_eq!(ticks(1000, 32_768), 32_768);
+        assert_eq!(ticks(1, 32_768), 33);
+        for ms in [1, 10, 167, 500, 1_000_000] {
+            assert!(millis(ticks(ms, 32_768), 32_768) >= ms);
+        }
+    }
+
+    #[test]
+    fn events_come_out_in_deadline_order() {
+        let mut schedule = Scheduler::<Event, 4>::new();
+        assert!(schedule.schedule(30, Event::Gravity));
+        assert!(schedule.schedule(10, Event::Input));
+        assert!(schedule.schedule(20, Event::Beep(3)));
+        assert_eq!(schedule.next_deadline(0), Some(10));
+        assert_eq!(schedule.pop_due(5), None);
+        assert_eq!(schedule.pop_due(25), Some(Event::Input));
+        assert_eq!(schedule.pop_due(25), Some(Event::Beep(3)));
+        assert_eq!(schedule.pop_due(25), None);
+        assert_eq!(schedule.pop_due(30), Some(Event::Gravity));
+        assert_eq!(schedule.next_deadline(30), None);
+    }
+
+    #[test]
+    fn rescheduling_a_variant_moves_it() {
+        let mut schedule = Scheduler::<Event, 2>::new();
+        schedule.schedule(10, Event::Beep(3));
+        schedule.schedule(50, Event::Beep(2));
+        assert_eq!(schedule.deadline(Event::Beep(0)), Some(50));
+        assert!(schedule.schedule_in(0, 1500, Event::Gravity));
+        // Both slots are taken by other variants
+        assert!(!schedule.schedule(5, Event::Input));
+        schedule.cancel(Event::Beep(0));
+        assert_eq!(schedule.pop_due(100), None);
+        assert!(schedule.schedule(5, Event::Input));
+        assert_eq!(schedule.pop_due(100), Some(Event::Input));
+    }
+
+    #[test]
+    fn deadlines_survive_the_clock_wrapping() {
+        let mut schedule = Scheduler::<Event, 2>::new();
+        let now = u32::MAX - 5;
+        schedule.schedule_in(now, 10, Event::Gravity);
+        schedule.schedule_in(now, 2, Event::Input);
+        assert!(!reached(now, now.wrapping_add(10)));
+        assert_eq!(schedule.pop_due(now), None);
+        assert_eq!(schedule.pop_due(u32::MAX), Some(Event::Input));
+        assert_eq!(schedule.pop_due(u32::MAX), None);
+        assert_eq!(schedule.pop_due(4), Some(Event::Gravity));
+    }
+}
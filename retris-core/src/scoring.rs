@@ -0,0 +1,278 @@
+//! Points for clearing lines and dropping pieces
+//!
+//! Line clears are worth more at higher levels, and the level goes up after a
+//! set number of lines. The guideline table is scored for four-row clears, so
+//! the micro board, which clears at most two rows with one piece, has its own
+//! scaled table. T-spins score through their own tables, back-to-back
+//! difficult clears get a bonus, and each lock in a combo adds points. An
+//! all-clear adds its own bonus after the back-to-back bonus, with a fixed
+//! value when it is back-to-back itself.
+//!
+//! Scoring reference: <https://tetris.wiki/Scoring>
+use crate::game::TSpin;
+
+/// How many points each clear and drop is worth
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScoreTable {
+    /// Points for clearing each number of rows at once, multiplied by the level.
+    /// Clears bigger than the table score its last entry.
+    pub lines: &'static [u32],
+    /// Points for a T-spin clearing each number of rows, used instead of `lines`
+    pub t_spin: &'static [u32],
+    /// Points for a mini T-spin clearing each number of rows, used instead of `lines`
+    pub mini_t_spin: &'static [u32],
+    /// Bonus points for an all-clear of each number of rows, added to the clear
+    pub all_clear: &'static [u32],
+    /// Bonus points for a back-to-back all-clear, used instead of `all_clear`
+    pub back_to_back_all_clear: u32,
+    /// Points for each step of a combo, multiplied by the level
+    pub combo: u32,
+    /// Percent of the clear points scored for a back-to-back difficult clear
+    pub back_to_back: u32,
+    /// Points for each row soft dropped
+    pub soft_drop: u32,
+    /// Points for each row hard dropped
+    pub hard_drop: u32,
+    /// Lines to clear before the level goes up
+    pub lines_per_level: u32,
+}
+
+impl ScoreTable {
+    /// Guideline single, double, triple, and tetris scoring
+    pub const GUIDELINE: ScoreTable = ScoreTable {
+        lines: &[0, 100, 300, 500, 800],
+        t_spin: &[400, 800, 1200, 1600],
+        mini_t_spin: &[100, 200, 400],
+        all_clear: &[0, 800, 1200, 1800, 2000],
+        back_to_back_all_clear: 3200,
+        combo: 50,
+        back_to_back: 150,
+        soft_drop: 1,
+        hard_drop: 2,
+        lines_per_level: 10,
+    };
+    /// Scoring for the 2-row clears of the micro piece set
+    ///
+    /// Each clear is worth the guideline clear that fills the same share of
+    /// the biggest clear, so a double scores like a tetris. The micro pieces
+    /// have no T piece to spin, so the T-spin tables are empty.
+    pub const MICRO: ScoreTable = ScoreTable {
+        lines: &[0, 300, 800],
+        t_spin: &[],
+        mini_t_spin: &[],
+        all_clear: &[0, 1200, 2000],
+        back_to_back_all_clear: 3200,
+        combo: 50,
+        back_to_back: 150,
+        soft_drop: 1,
+        hard_drop: 2,
+        lines_per_level: 5,
+    };
+    /// Points for clearing rows at once
+    ///
+    /// # Arguments
+    /// * `lines` - Number of rows cleared
+    /// * `level` - The level the rows were cleared at
+    ///
+    /// # Returns
+    /// * The points for the clear
+    pub fn line_points(&self, lines: u8, level: u32) -> u32 {
+        entry(self.lines, lines).saturating_mul(level)
+    }
+    /// Points for a clear, counting T-spins
+    ///
+    /// # Arguments
+    /// * `lines` - Number of rows cleared, may be zero for a T-spin
+    /// * `t_spin` - The kind of T-spin the piece locked with, if any
+    /// * `level` - The level the rows were cleared at
+    ///
+    /// # Returns
+    /// * The points for the clear
+    pub fn clear_points(&self, lines: u8, t_spin: Option<TSpin>, level: u32) -> u32 {
+        let table = match t_spin {
+            None => self.lines,
+            Some(TSpin::Mini) => self.mini_t_spin,
+            Some(TSpin::Full) => self.t_spin,
+        };
+        entry(table, lines).saturating_mul(level)
+    }
+    /// Bonus points for a clear that left the board empty
+    ///
+    /// The bonus is added after the back-to-back bonus rather than multiplied
+    /// by it.
+    ///
+    /// # Arguments
+    /// * `lines` - Number of rows cleared
+    /// * `back_to_back` - The clear was back-to-back with another difficult clear
+    /// * `level` - The level the rows were cleared at
+    ///
+    /// # Returns
+    /// * The bonus for the all-clear
+    pub fn all_clear_points(&self, lines: u8, back_to_back: bool, level: u32) -> u32 {
+        let points = if back_to_back {
+            self.back_to_back_all_clear
+        } else {
+            entry(self.all_clear, lines)
+        };
+        points.saturating_mul(level)
+    }
+    /// Points for a clear made back-to-back with another difficult clear
+    ///
+    /// # Arguments
+    /// * `points` - The points for the clear on its own
+    ///
+    /// # Returns
+    /// * The points with the back-to-back bonus
+    pub fn back_to_back_points(&self, points: u32) -> u32 {
+        points.saturating_mul(self.back_to_back) / 100
+    }
+    /// Points for a lock in a combo
+    ///
+    /// # Arguments
+    /// * `combo` - Locks in a row that cleared rows before this one
+    /// * `level` - The level the rows were cleared at
+    ///
+    /// # Returns
+    /// * The points for the combo, zero for the first clear
+    pub fn combo_points(&self, combo: u8, level: u32) -> u32 {
+        self.combo
+            .saturating_mul(combo as u32)
+            .saturating_mul(level)
+    }
+    /// Points for dropping a piece
+    ///
+    /// # Arguments
+    /// * `soft_drop` - Rows soft dropped
+    /// * `hard_drop` - Rows hard dropped
+    ///
+    /// # Returns
+    /// * The points for the drops
+    pub fn drop_points(&self, soft_drop: u8, hard_drop: u8) -> u32 {
+        self.soft_drop * soft_drop as u32 + self.hard_drop * hard_drop as u32
+    }
+    /// Level reached after clearing a number of lines
+    ///
+    /// # Arguments
+    /// * `lines` - Total rows cleared in the game
+    ///
+    /// # Returns
+    /// * The level, starting from 1
+    pub fn level(&self, lines: u32) -> u32 {
+        1 + lines / self.lines_per_level.max(1)
+    }
+}
+
+impl Default for ScoreTable {
+    fn default() -> Self {
+        Self::MICRO
+    }
+}
+
+/// Look up the points for a number of rows
+///
+/// # Arguments
+/// * `table` - Points for each number of rows
+/// * `lines` - Number of rows cleared
+///
+/// # Returns
+/// * The entry for `lines`, the last entry for bigger clears, or zero for an empty table
+fn entry(table: &[u32], lines: u8) -> u32 {
+    table
+        .get(lines as usize)
+        .or(table.last())
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Write a number out in decimal digits
+///
+/// Lets the firmware scroll a score without an allocator.
+///
+/// # Arguments
+/// * `value` - The number to write
+/// * `buf` - Space for the digits, big enough for any `u32`
+///
+/// # Returns
+/// * The digits, borrowed from the end of `buf`
+pub fn digits(mut value: u32, buf: &mut [u8; 10]) -> &str {
+    let mut start = buf.len();
+    loop {
+        start -= 1;
+        buf[start] = b'0' + (value % 10) as u8;
+        value /= 10;
+        if value == 0 {
+            break;
+        }
+    }
+    // Only ASCII digits were written
+    core::str::from_utf8(&buf[start..]).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guideline_lines_scale_with_level() {
+        let table = ScoreTable::GUIDELINE;
+        let points: [u32; 5] = core::array::from_fn(|lines| table.line_points(lines as u8, 1));
+        assert_eq!(points, [0, 100, 300, 500, 800]);
+        assert_eq!(table.line_points(4, 3), 2400);
+        assert_eq!(table.drop_points(5, 0), 5);
+        assert_eq!(table.drop_points(2, 18), 38);
+    }
+
+    #[test]
+    fn micro_double_scores_like_a_tetris() {
+        let table = ScoreTable::MICRO;
+        assert_eq!(
+            table.line_points(2, 1),
+            ScoreTable::GUIDELINE.line_points(4, 1)
+        );
+        // Cascades can clear more rows than a piece covers
+        assert_eq!(table.line_points(3, 2), 1600);
+    }
+
+    #[test]
+    fn t_spins_and_all_clears_use_their_tables() {
+        let table = ScoreTable::GUIDELINE;
+        assert_eq!(table.clear_points(0, Some(TSpin::Full), 1), 400);
+        assert_eq!(table.clear_points(2, Some(TSpin::Full), 2), 2400);
+        assert_eq!(table.clear_points(1, Some(TSpin::Mini), 1), 200);
+        assert_eq!(table.clear_points(3, None, 1), 500);
+        assert_eq!(table.all_clear_points(4, false, 1), 2000);
+        assert_eq!(table.all_clear_points(2, false, 3), 3600);
+        // A back-to-back all-clear has its own value
+        assert_eq!(table.all_clear_points(4, true, 1), 3200);
+        assert_eq!(ScoreTable::MICRO.all_clear_points(2, false, 1), 2000);
+        // The micro pieces cannot T-spin
+        assert_eq!(ScoreTable::MICRO.clear_points(2, Some(TSpin::Full), 1), 0);
+    }
+
+    #[test]
+    fn chains_add_bonus_points() {
+        let table = ScoreTable::GUIDELINE;
+        assert_eq!(table.back_to_back_points(800), 1200);
+        assert_eq!(table.back_to_back_points(1200), 1800);
+        assert_eq!(table.combo_points(0, 3), 0);
+        assert_eq!(table.combo_points(4, 2), 400);
+    }
+
+    #[test]
+    fn level_goes_up_every_few_lines() {
+        let table = ScoreTable::GUIDELINE;
+        assert_eq!(table.level(0), 1);
+        assert_eq!(table.level(9), 1);
+        assert_eq!(table.level(10), 2);
+        assert_eq!(table.level(35), 4);
+        assert_eq!(ScoreTable::MICRO.level(5), 2);
+    }
+
+    #[test]
+    fn digits_writes_decimal() {
+        let mut buf = [0; 10];
+        assert_eq!(digits(0, &mut buf), "0");
+        assert_eq!(digits(1230, &mut buf), "1230");
+        assert_eq!(digits(u32::MAX, &mut buf), "4294967295");
+    }
+}
@@ -0,0 +1,27 @@
+//! Player settings
+//!
+//! Preferences that change how the game looks or feels to play without
+//! changing its rules, so they can differ between players on the same board.
+use crate::input::AutoRepeat;
+use crate::tilt::TiltProfile;
+
+/// Player settings
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Settings {
+    /// Draw a dim ghost where the falling piece would land
+    pub ghost: bool,
+    /// How held movement buttons repeat
+    pub auto_repeat: AutoRepeat,
+    /// How tilting the board moves and drops pieces
+    pub tilt: TiltProfile,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            ghost: true,
+            auto_repeat: AutoRepeat::default(),
+            tilt: TiltProfile::default(),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Beep patterns for what happens in the game
+//!
+//! The speaker can only beep at one pitch, so events are told apart by how
+//! many beeps they get and how far apart the beeps are. The firmware plays a
+//! pattern one beep at a time from its scheduler instead of blocking.
+use crate::game::StepOutcome;
+
+/// A run of evenly spaced beeps
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BeepPattern {
+    /// Number of beeps
+    pub beeps: u8,
+    /// Milliseconds from the start of one beep to the next
+    pub gap_ms: u16,
+}
+
+impl BeepPattern {
+    /// Quick trill for emptying the board
+    pub const ALL_CLEAR: BeepPattern = BeepPattern {
+        beeps: 6,
+        gap_ms: 40,
+    };
+    /// Slow triple beep for a T-spin, even without a clear
+    pub const T_SPIN: BeepPattern = BeepPattern {
+        beeps: 3,
+        gap_ms: 150,
+    };
+    /// One beep for each row cleared
+    ///
+    /// # Arguments
+    /// * `lines` - Number of rows cleared
+    ///
+    /// # Returns
+    /// * The pattern for the clear
+    pub const fn lines(lines: u8) -> BeepPattern {
+        BeepPattern {
+            beeps: lines,
+            gap_ms: 75,
+        }
+    }
+    /// Pattern to play after a step, if any
+    ///
+    /// # Arguments
+    /// * `outcome` - What happened during the step
+    ///
+    /// # Returns
+    /// * The pattern for the most special thing that happened, or None if
+    ///   nothing worth a beep happened
+    pub fn for_outcome(outcome: &StepOutcome) -> Option<BeepPattern> {
+        if outcome.all_clear {
+            Some(Self::ALL_CLEAR)
+        } else if outcome.t_spin.is_some() {
+            Some(Self::T_SPIN)
+        } else if outcome.lines_cleared > 0 {
+            Some(Self::lines(outcome.lines_cleared))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::TSpin;
+
+    #[test]
+    fn special_clears_get_their_own_pattern() {
+        let outcome = |lines_cleared, t_spin, all_clear| StepOutcome {
+            lines_cleared,
+            t_spin,
+            all_clear,
+            ..StepOutcome::default()
+        };
+        assert_eq!(BeepPattern::for_outcome(&outcome(0, None, false)), None);
+        assert_eq!(
+            BeepPattern::for_outcome(&outcome(2, None, false)),
+            Some(BeepPattern::lines(2))
+        );
+        assert_eq!(
+            BeepPattern::for_outcome(&outcome(0, Some(TSpin::Mini), false)),
+            Some(BeepPattern::T_SPIN)
+        );
+        assert_eq!(
+            BeepPattern::for_outcome(&outcome(2, Some(TSpin::Full), true)),
+            Some(BeepPattern::ALL_CLEAR)
+        );
+    }
+}
@@ -0,0 +1,569 @@
+//! Tetrominos shape
+//!
+//! Names for shapes taken from <https://en.wikipedia.org/wiki/Tetromino>
+//!
+//! Two piece sets are available. The micro set squeezes each piece into a 2x2
+//! box so it has room to fall on the 5x5 pixel display, which means a clear
+//! only needs two rows. The SRS set has the seven standard four-cell pieces
+//! with the rotation states of the Super Rotation System, for larger boards.
+//!
+//! SRS reference: <https://tetris.wiki/Super_Rotation_System>
+
+use crate::rand::random_range;
+use nanorand::Pcg64;
+
+/// Kind of tetromino a piece is, named after the standard piece it stands in for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PieceKind {
+    /// Straight piece
+    I,
+    /// Square piece
+    O,
+    /// T shape piece
+    T,
+    /// S shape piece
+    S,
+    /// Z shape piece
+    Z,
+    /// J shape piece
+    J,
+    /// L shape piece
+    L,
+}
+
+/// Rotation state of a piece, named as in the Super Rotation System
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rotation {
+    /// State the piece spawns in, SRS state 0
+    #[default]
+    Spawn,
+    /// One turn clockwise from spawn, SRS state R
+    Right,
+    /// Two turns from spawn, SRS state 2
+    Reverse,
+    /// One turn counter-clockwise from spawn, SRS state L
+    Left,
+}
+
+impl Rotation {
+    /// Rotation state after turning clockwise
+    ///
+    /// # Returns
+    /// * The next rotation state clockwise
+    pub const fn clockwise(self) -> Self {
+        match self {
+            Rotation::Spawn => Rotation::Right,
+            Rotation::Right => Rotation::Reverse,
+            Rotation::Reverse => Rotation::Left,
+            Rotation::Left => Rotation::Spawn,
+        }
+    }
+    /// Rotation state after turning counter-clockwise
+    ///
+    /// # Returns
+    /// * The next rotation state counter-clockwise
+    pub const fn counter_clockwise(self) -> Self {
+        match self {
+            Rotation::Spawn => Rotation::Left,
+            Rotation::Right => Rotation::Spawn,
+            Rotation::Reverse => Rotation::Right,
+            Rotation::Left => Rotation::Reverse,
+        }
+    }
+    /// Rotation state after turning 180 degrees
+    ///
+    /// # Returns
+    /// * The opposite rotation state
+    pub const fn flip(self) -> Self {
+        self.clockwise().clockwise()
+    }
+}
+
+/// Offset tried when rotating a piece, as columns right and rows up
+///
+/// Rows count up to match the published SRS tables, the opposite of board rows.
+pub type Kick = (isize, isize);
+
+/// Kicks for every micro piece: in place, away from either wall, then up a row
+const MICRO_KICKS: [Kick; 4] = [(0, 0), (-1, 0), (1, 0), (0, 1)];
+
+/// SRS kicks for the J, L, S, T, and Z pieces, in the order of [`transition`]
+const SRS_JLSTZ_KICKS: [[Kick; 5]; 8] = [
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+];
+
+/// SRS kicks for the I piece, in the order of [`transition`]
+const SRS_I_KICKS: [[Kick; 5]; 8] = [
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+];
+
+/// Kicks for 180 degree turns, in the order of [`half_turn`]
+///
+/// SRS has no 180 degree turn, so these follow the SRS+ table used by TETR.IO.
+const SRS_180_KICKS: [[Kick; 6]; 4] = [
+    [(0, 0), (0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)],
+    [(0, 0), (1, 0), (1, 2), (1, 1), (0, 2), (0, 1)],
+    [(0, 0), (0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)],
+    [(0, 0), (-1, 0), (-1, 2), (-1, 1), (0, 2), (0, 1)],
+];
+
+/// SRS kicks for the O piece, which never needs to move
+const SRS_O_KICKS: [Kick; 1] = [(0, 0)];
+
+/// Index of a quarter turn in the SRS kick tables
+///
+/// Ordered 0→R, R→0, R→2, 2→R, 2→L, L→2, L→0, 0→L.
+///
+/// # Arguments
+/// * `from` - Rotation state before turning
+/// * `to` - Rotation state after turning
+///
+/// # Returns
+/// * The row of the kick table, or None if the states are not a quarter turn apart
+const fn transition(from: Rotation, to: Rotation) -> Option<usize> {
+    match (from, to) {
+        (Rotation::Spawn, Rotation::Right) => Some(0),
+        (Rotation::Right, Rotation::Spawn) => Some(1),
+        (Rotation::Right, Rotation::Reverse) => Some(2),
+        (Rotation::Reverse, Rotation::Right) => Some(3),
+        (Rotation::Reverse, Rotation::Left) => Some(4),
+        (Rotation::Left, Rotation::Reverse) => Some(5),
+        (Rotation::Left, Rotation::Spawn) => Some(6),
+        (Rotation::Spawn, Rotation::Left) => Some(7),
+        _ => None,
+    }
+}
+
+/// Index of a half turn in the 180 degree kick table
+///
+/// Ordered 0→2, R→L, 2→0, L→R.
+///
+/// # Arguments
+/// * `from` - Rotation state before turning
+/// * `to` - Rotation state after turning
+///
+/// # Returns
+/// * The row of the kick table, or None if the states are not a half turn apart
+const fn half_turn(from: Rotation, to: Rotation) -> Option<usize> {
+    match (from, to) {
+        (Rotation::Spawn, Rotation::Reverse) => Some(0),
+        (Rotation::Right, Rotation::Left) => Some(1),
+        (Rotation::Reverse, Rotation::Spawn) => Some(2),
+        (Rotation::Left, Rotation::Right) => Some(3),
+        _ => None,
+    }
+}
+
+/// Shape of a piece in a square bounding box
+///
+/// Only the top left `size` by `size` cells of `cells` are used, indexed as row and
+/// then column. Rotating turns the shape within that box.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shape {
+    /// Width and height of the bounding box
+    pub size: usize,
+    /// Filled cells of the piece, 1 if filled and 0 if empty
+    pub cells: [[u8; 4]; 4],
+}
+
+impl Shape {
+    /// Rotate a shape clockwise 90 degrees
+    ///
+    /// # Returns
+    /// * That shape rotated clockwise 90 degrees within its bounding box
+    pub const fn rotate_clockwise(self) -> Self {
+        let mut cells = [[0; 4]; 4];
+        let mut row = 0;
+        while row < self.size {
+            let mut col = 0;
+            while col < self.size {
+                cells[row][col] = self.cells[self.size - 1 - col][row];
+                col += 1;
+            }
+            row += 1;
+        }
+        Self {
+            size: self.size,
+            cells,
+        }
+    }
+    /// Rotate a shape counter-clockwise 90 degrees
+    ///
+    /// # Returns
+    /// * That shape rotated counter-clockwise 90 degrees within its bounding box
+    pub const fn rotate_counter_clockwise(self) -> Self {
+        self.rotate_180().rotate_clockwise()
+    }
+    /// Rotate a shape 180 degrees
+    ///
+    /// # Returns
+    /// * That shape turned upside down within its bounding box
+    pub const fn rotate_180(self) -> Self {
+        self.rotate_clockwise().rotate_clockwise()
+    }
+    /// Filled cells of the shape
+    ///
+    /// # Returns
+    /// * The row and column within the bounding box of every filled cell
+    pub fn filled(self) -> impl Iterator<Item = (usize, usize)> {
+        (0..self.size)
+            .flat_map(move |row| (0..self.size).map(move |col| (row, col)))
+            .filter(move |&(row, col)| self.cells[row][col] == 1)
+    }
+}
+
+/// Straight shape piece
+const STRAIGHT: Shape = Shape {
+    size: 2,
+    cells: [[1, 0, 0, 0], [1, 0, 0, 0], [0; 4], [0; 4]],
+};
+
+/// Square shape piece
+const SQUARE: Shape = Shape {
+    size: 2,
+    cells: [[1, 1, 0, 0], [1, 1, 0, 0], [0; 4], [0; 4]],
+};
+
+/// Botton left corner shape piece, formerly L
+const CORNER_L: Shape = Shape {
+    size: 2,
+    cells: [[1, 0, 0, 0], [1, 1, 0, 0], [0; 4], [0; 4]],
+};
+
+/// Corners piece, formerly S
+const DIAGONAL_S: Shape = Shape {
+    size: 2,
+    cells: [[0, 1, 0, 0], [1, 0, 0, 0], [0; 4], [0; 4]],
+};
+
+/// Top right corner shape piece, formerly T
+const CORNER_T: Shape = Shape {
+    size: 2,
+    cells: [[1, 1, 0, 0], [0, 1, 0, 0], [0; 4], [0; 4]],
+};
+
+/// SRS I piece, lying flat in the second row of a 4x4 box
+const SRS_I: Shape = Shape {
+    size: 4,
+    cells: [[0, 0, 0, 0], [1, 1, 1, 1], [0, 0, 0, 0], [0, 0, 0, 0]],
+};
+
+/// SRS O piece, which never changes when rotated
+const SRS_O: Shape = Shape {
+    size: 2,
+    cells: [[1, 1, 0, 0], [1, 1, 0, 0], [0; 4], [0; 4]],
+};
+
+/// SRS T piece, pointing up
+const SRS_T: Shape = Shape {
+    size: 3,
+    cells: [[0, 1, 0, 0], [1, 1, 1, 0], [0; 4], [0; 4]],
+};
+
+/// SRS S piece
+const SRS_S: Shape = Shape {
+    size: 3,
+    cells: [[0, 1, 1, 0], [1, 1, 0, 0], [0; 4], [0; 4]],
+};
+
+/// SRS Z piece
+const SRS_Z: Shape = Shape {
+    size: 3,
+    cells: [[1, 1, 0, 0], [0, 1, 1, 0], [0; 4], [0; 4]],
+};
+
+/// SRS J piece
+const SRS_J: Shape = Shape {
+    size: 3,
+    cells: [[1, 0, 0, 0], [1, 1, 1, 0], [0; 4], [0; 4]],
+};
+
+/// SRS L piece
+const SRS_L: Shape = Shape {
+    size: 3,
+    cells: [[0, 0, 1, 0], [1, 1, 1, 0], [0; 4], [0; 4]],
+};
+
+/// Set of pieces a game is played with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PieceSet {
+    /// 2x2 stand-ins for the pieces, sized for the 5x5 pixel display
+    #[default]
+    Micro,
+    /// The seven standard tetrominos with Super Rotation System states
+    Srs,
+}
+
+impl PieceSet {
+    /// Kinds of piece in the set
+    ///
+    /// # Returns
+    /// * Every kind of piece the set can spawn
+    pub const fn kinds(self) -> &'static [PieceKind] {
+        match self {
+            PieceSet::Micro => &[
+                PieceKind::I,
+                PieceKind::O,
+                PieceKind::T,
+                PieceKind::S,
+                PieceKind::L,
+            ],
+            PieceSet::Srs => &[
+                PieceKind::I,
+                PieceKind::O,
+                PieceKind::T,
+                PieceKind::S,
+                PieceKind::Z,
+                PieceKind::J,
+                PieceKind::L,
+            ],
+        }
+    }
+    /// Get the spawn shape of a tetromino
+    ///
+    /// The micro set has no Z or J piece, so those fall back to its S and L
+    /// stand-ins.
+    ///
+    /// # Arguments
+    /// * `kind` - The kind of tetromino
+    ///
+    /// # Returns
+    /// * The tetromino shape const
+    pub const fn shape(self, kind: PieceKind) -> Shape {
+        match self {
+            PieceSet::Micro => match kind {
+                PieceKind::I => STRAIGHT,
+                PieceKind::O => SQUARE,
+                PieceKind::T => CORNER_T,
+                PieceKind::S | PieceKind::Z => DIAGONAL_S,
+                PieceKind::J | PieceKind::L => CORNER_L,
+            },
+            PieceSet::Srs => match kind {
+                PieceKind::I => SRS_I,
+                PieceKind::O => SRS_O,
+                PieceKind::T => SRS_T,
+                PieceKind::S => SRS_S,
+                PieceKind::Z => SRS_Z,
+                PieceKind::J => SRS_J,
+                PieceKind::L => SRS_L,
+            },
+        }
+    }
+    /// Column a piece's bounding box spawns in on a board `width` columns wide
+    ///
+    /// # Arguments
+    /// * `width` - Number of columns on the board
+    /// * `shape` - The spawn shape of the piece
+    ///
+    /// # Returns
+    /// * The leftmost column of the bounding box
+    pub const fn spawn_col(self, width: usize, shape: Shape) -> usize {
+        match self {
+            // Rounded right of center, as on the 5x5 pixel display
+            PieceSet::Micro => (width - 1) / 2,
+            // Rounded left of center, as in the guideline
+            PieceSet::Srs => (width - shape.size) / 2,
+        }
+    }
+    /// Kicks to try, in order, when rotating a piece between two states
+    ///
+    /// # Arguments
+    /// * `kind` - The kind of piece rotating
+    /// * `from` - Rotation state before turning
+    /// * `to` - Rotation state after turning
+    ///
+    /// # Returns
+    /// * Offsets to test the rotated piece at, the first that fits is used
+    pub const fn kicks(self, kind: PieceKind, from: Rotation, to: Rotation) -> &'static [Kick] {
+        match self {
+            PieceSet::Micro => &MICRO_KICKS,
+            PieceSet::Srs => match (kind, transition(from, to), half_turn(from, to)) {
+                (PieceKind::O, _, _) => &SRS_O_KICKS,
+                (PieceKind::I, Some(index), _) => &SRS_I_KICKS[index],
+                (_, Some(index), _) => &SRS_JLSTZ_KICKS[index],
+                (_, None, Some(index)) => &SRS_180_KICKS[index],
+                (_, None, None) => &SRS_O_KICKS,
+            },
+        }
+    }
+}
+
+/// Get a random tetromino from the micro set
+///
+/// Weighted against the straight piece (1/10 chance to get straight piece)
+///
+/// # Arguments
+/// * `rng` - A mutable reference to a Pcg64 random number generator.
+///
+/// # Returns
+/// * A random tetromino kind
+pub fn get_random_tetromino(rng: &mut Pcg64) -> PieceKind {
+    let rand = random_range(rng, 0, 10);
+    match rand {
+        0 => PieceKind::L,
+        1 => PieceKind::S,
+        2 => PieceKind::T,
+        3 => PieceKind::O,
+        4 => PieceKind::I,
+        5 => PieceKind::L,
+        6 => PieceKind::S,
+        7 => PieceKind::T,
+        8 => PieceKind::L,
+        9 => PieceKind::L,
+        _ => PieceKind::T,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Shape from rows of `#` for filled cells and `.` for empty ones
+    fn shape_from(rows: &[&str]) -> Shape {
+        let mut cells = [[0; 4]; 4];
+        for (row, line) in rows.iter().enumerate() {
+            for (col, c) in line.chars().enumerate() {
+                cells[row][col] = u8::from(c == '#');
+            }
+        }
+        Shape {
+            size: rows.len(),
+            cells,
+        }
+    }
+
+    #[test]
+    fn micro_rotation_matches_2x2_permutation() {
+        assert_eq!(STRAIGHT.rotate_clockwise(), shape_from(&["##", ".."]));
+        assert_eq!(CORNER_L.rotate_clockwise(), shape_from(&["##", "#."]));
+        assert_eq!(DIAGONAL_S.rotate_clockwise(), shape_from(&["#.", ".#"]));
+        assert_eq!(CORNER_T.rotate_clockwise(), shape_from(&[".#", "##"]));
+        assert_eq!(SQUARE.rotate_clockwise(), SQUARE);
+    }
+
+    #[test]
+    fn rotations_undo_each_other() {
+        for set in [PieceSet::Micro, PieceSet::Srs] {
+            for &kind in set.kinds() {
+                let shape = set.shape(kind);
+                let turned = shape.rotate_clockwise();
+                assert_eq!(turned.rotate_counter_clockwise(), shape);
+                assert_eq!(shape.rotate_counter_clockwise().rotate_clockwise(), shape);
+                assert_eq!(turned.rotate_180(), shape.rotate_counter_clockwise());
+                assert_eq!(shape.rotate_180().rotate_180(), shape);
+            }
+        }
+        let mut rotation = Rotation::Spawn;
+        for _ in 0..4 {
+            assert_eq!(rotation.clockwise().counter_clockwise(), rotation);
+            assert_eq!(
+                rotation.flip(),
+                rotation.counter_clockwise().counter_clockwise()
+            );
+            rotation = rotation.clockwise();
+        }
+    }
+
+    #[test]
+    fn srs_half_turns_use_180_kicks() {
+        let kicks = PieceSet::Srs.kicks(PieceKind::T, Rotation::Spawn, Rotation::Reverse);
+        assert_eq!(kicks.len(), 6);
+        assert_eq!(kicks[0], (0, 0));
+        let kicks = PieceSet::Srs.kicks(PieceKind::I, Rotation::Left, Rotation::Right);
+        assert_eq!(kicks.len(), 6);
+    }
+
+    #[test]
+    fn srs_pieces_have_four_cells_in_every_state() {
+        for &kind in PieceSet::Srs.kinds() {
+            let mut shape = PieceSet::Srs.shape(kind);
+            for _ in 0..4 {
+                assert_eq!(shape.filled().count(), 4, "{:?}", kind);
+                shape = shape.rotate_clockwise();
+            }
+            assert_eq!(shape, PieceSet::Srs.shape(kind));
+        }
+    }
+
+    #[test]
+    fn srs_t_states() {
+        let spawn = PieceSet::Srs.shape(PieceKind::T);
+        assert_eq!(spawn, shape_from(&[".#.", "###", "..."]));
+        let right = spawn.rotate_clockwise();
+        assert_eq!(right, shape_from(&[".#.", ".##", ".#."]));
+        let reverse = right.rotate_clockwise();
+        assert_eq!(reverse, shape_from(&["...", "###", ".#."]));
+        let left = reverse.rotate_clockwise();
+        assert_eq!(left, shape_from(&[".#.", "##.", ".#."]));
+    }
+
+    #[test]
+    fn srs_i_states() {
+        let spawn = PieceSet::Srs.shape(PieceKind::I);
+        let right = spawn.rotate_clockwise();
+        assert_eq!(right, shape_from(&["..#.", "..#.", "..#.", "..#."]));
+        let reverse = right.rotate_clockwise();
+        assert_eq!(reverse, shape_from(&["....", "....", "####", "...."]));
+        let left = reverse.rotate_clockwise();
+        assert_eq!(left, shape_from(&[".#..", ".#..", ".#..", ".#.."]));
+    }
+
+    #[test]
+    fn srs_spawn_shapes() {
+        let set = PieceSet::Srs;
+        assert_eq!(set.shape(PieceKind::S), shape_from(&[".##", "##.", "..."]));
+        assert_eq!(set.shape(PieceKind::Z), shape_from(&["##.", ".##", "..."]));
+        assert_eq!(set.shape(PieceKind::J), shape_from(&["#..", "###", "..."]));
+        assert_eq!(set.shape(PieceKind::L), shape_from(&["..#", "###", "..."]));
+        assert_eq!(set.shape(PieceKind::O), shape_from(&["##", "##"]));
+    }
+
+    #[test]
+    fn srs_spawn_columns_on_standard_board() {
+        let set = PieceSet::Srs;
+        assert_eq!(set.spawn_col(10, set.shape(PieceKind::I)), 3);
+        assert_eq!(set.spawn_col(10, set.shape(PieceKind::T)), 3);
+        assert_eq!(set.spawn_col(10, set.shape(PieceKind::O)), 4);
+    }
+
+    #[test]
+    fn srs_kicks_are_mirrored_between_directions() {
+        let set = PieceSet::Srs;
+        let mut from = Rotation::Spawn;
+        for _ in 0..4 {
+            let to = from.clockwise();
+            for kind in [PieceKind::T, PieceKind::I] {
+                let forward = set.kicks(kind, from, to);
+                let back = set.kicks(kind, to, from);
+                assert_eq!(forward.len(), 5);
+                for (&(x, y), &(back_x, back_y)) in forward.iter().zip(back) {
+                    assert_eq!((x, y), (-back_x, -back_y));
+                }
+            }
+            from = to;
+        }
+    }
+
+    #[test]
+    fn o_piece_and_micro_kicks_start_in_place() {
+        let (from, to) = (Rotation::Spawn, Rotation::Right);
+        assert_eq!(PieceSet::Srs.kicks(PieceKind::O, from, to), &[(0, 0)]);
+        for &kind in PieceSet::Micro.kinds() {
+            assert_eq!(PieceSet::Micro.kicks(kind, from, to)[0], (0, 0));
+        }
+    }
+}
@@ -0,0 +1,421 @@
+//! Moving pieces by tilting the board
+//!
+//! The firmware reads the accelerometer once per input tick and hands the
+//! reading to [`Tilt`]. The first readings are averaged into the neutral pose
+//! the player holds the board in, and every later reading is turned into a
+//! roll (left and right) and a pitch (forward and back) measured from that
+//! pose. Small leans inside a dead zone count as level, so the board does not
+//! need to be held perfectly still. A lean has to pass the engage angle to
+//! start moving and fall back under the lower release angle to stop, so a
+//! board held near the threshold does not flicker between the two.
+//!
+//! Leaning acts like holding a movement button: one move as the lean starts,
+//! then after a delay more moves, coming faster the further the board is
+//! tilted. Leaning left or right moves the piece and leaning forward soft
+//! drops it. Leaning back flips the piece around, once per lean, since a
+//! rotation that repeats would be hard to stop on the right side.
+use libm::{atan2f, copysignf, fabsf, hypotf, roundf};
+
+use crate::controller::Actions;
+use crate::game::Action;
+use crate::schedule::reached;
+
+/// An accelerometer reading, x to the right, y toward the top edge and z out
+/// of the display, in milli-g
+pub type Reading = (i32, i32, i32);
+
+/// How tilting the board feels, with angles in degrees from the neutral pose
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TiltProfile {
+    /// Leans up to this angle count as level
+    pub dead_zone_deg: u8,
+    /// Angle past the dead zone a lean has to reach to start moving
+    pub engage_deg: u8,
+    /// Angle past the dead zone a lean has to fall under to stop, at most
+    /// `engage_deg`
+    pub release_deg: u8,
+    /// Angle past the dead zone where repeats reach their fastest rate
+    pub full_deg: u8,
+    /// Milliseconds from the start of a lean to the first repeat
+    pub delay_ms: u32,
+    /// Milliseconds between repeats at the engage angle
+    pub slow_rate_ms: u32,
+    /// Milliseconds between repeats at the full angle, at least 1
+    pub fast_rate_ms: u32,
+}
+
+impl TiltProfile {
+    /// Gentle handling for the 5x5 board, matching the LED auto-repeat at the
+    /// engage angle and nearly four times as fast when tilted steeply
+    pub const LED: TiltProfile = TiltProfile {
+        dead_zone_deg: 5,
+        engage_deg: 10,
+        release_deg: 5,
+        full_deg: 30,
+        delay_ms: 300,
+        slow_rate_ms: 150,
+        fast_rate_ms: 40,
+    };
+    /// Milliseconds between repeats at an angle
+    ///
+    /// # Arguments
+    /// * `angle` - Degrees past the dead zone, at least the engage angle
+    ///
+    /// # Returns
+    /// * The rate, scaled from the slow rate at the engage angle to the fast
+    ///   rate at the full angle
+    pub fn rate_ms(&self, angle: f32) -> u32 {
+        let span = f32::from(self.full_deg.saturating_sub(self.engage_deg)).max(1.0);
+        let scale = ((angle - f32::from(self.engage_deg)) / span).clamp(0.0, 1.0);
+        let (slow, fast) = (self.slow_rate_ms as f32, self.fast_rate_ms as f32);
+        (roundf(slow + (fast - slow) * scale) as u32).max(1)
+    }
+}
+
+impl Default for TiltProfile {
+    fn default() -> Self {
+        Self::LED
+    }
+}
+
+/// Roll and pitch of a reading in degrees
+///
+/// # Arguments
+/// * `reading` - The accelerometer reading
+///
+/// # Returns
+/// * The roll, negative to the left, and the pitch, negative forward
+pub fn angles(reading: Reading) -> (f32, f32) {
+    let (x, y, z) = (reading.0 as f32, reading.1 as f32, reading.2 as f32);
+    let roll = atan2f(x, hypotf(y, z));
+    let pitch = atan2f(y, hypotf(x, z));
+    (roll.to_degrees(), pitch.to_degrees())
+}
+
+/// Take the dead zone off an angle
+///
+/// # Arguments
+/// * `angle` - Degrees from the neutral pose
+/// * `dead_zone` - Degrees that count as level
+///
+/// # Returns
+/// * Degrees past the dead zone with the sign of `angle`, 0 inside it
+fn past_dead_zone(angle: f32, dead_zone: u8) -> f32 {
+    copysignf((fabsf(angle) - f32::from(dead_zone)).max(0.0), angle)
+}
+
+/// Check if holding a lean repeats an action
+///
+/// # Arguments
+/// * `action` - The action of the lean
+///
+/// # Returns
+/// * True for moves and drops, false for rotations, which act once per lean
+const fn repeats(action: Action) -> bool {
+    !matches!(
+        action,
+        Action::RotateClockwise | Action::RotateCounterClockwise | Action::Rotate180
+    )
+}
+
+/// One axis of the board leaning like a held button
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Lean {
+    /// Whether the lean is to the positive side, and when the next repeat is
+    /// due, while the axis is engaged
+    held: Option<(bool, u32)>,
+}
+
+impl Lean {
+    /// Feed the angle of the axis in
+    ///
+    /// # Arguments
+    /// * `now` - When the reading was taken, in milliseconds
+    /// * `angle` - Degrees past the dead zone
+    /// * `moves` - The action for leaning to the negative and the positive side
+    /// * `profile` - How tilting feels
+    /// * `actions` - Where to add the moves
+    fn update(
+        &mut self,
+        now: u32,
+        angle: f32,
+        moves: (Action, Action),
+        profile: &TiltProfile,
+        actions: &mut Actions,
+    ) {
+        let positive = angle > 0.0;
+        let magnitude = fabsf(angle);
+        // Let go once the lean falls under the release angle or flips over
+        if let Some((side, _)) = self.held {
+            if side != positive || magnitude < f32::from(profile.release_deg) {
+                self.held = None;
+            }
+        }
+        let action = if positive { moves.1 } else { moves.0 };
+        let Some((side, mut next)) = self.held else {
+            if magnitude >= f32::from(profile.engage_deg) {
+                actions.push(action);
+                self.held = Some((positive, now.wrapping_add(profile.delay_ms)));
+            }
+            return;
+        };
+        if !repeats(action) {
+            return;
+        }
+        while reached(now, next) {
+            if !actions.push(action) {
+                // Drop the backlog rather than moving in a burst later
+                next = now;
+            }
+            next = next.wrapping_add(profile.rate_ms(magnitude));
+        }
+        self.held = Some((side, next));
+    }
+}
+
+/// Turns accelerometer readings into moves, soft drops, and flips
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tilt {
+    /// How tilting feels
+    profile: TiltProfile,
+    /// Readings added up while measuring the neutral pose
+    sum: (i32, i32, i32),
+    /// Number of readings added up so far
+    samples: u32,
+    /// Roll and pitch of the neutral pose, once it is measured
+    neutral: Option<(f32, f32)>,
+    /// Leaning left or right
+    roll: Lean,
+    /// Leaning forward or back
+    pitch: Lean,
+}
+
+impl Tilt {
+    /// Readings averaged into the neutral pose
+    pub const CALIBRATION_SAMPLES: u32 = 16;
+
+    /// Create a tilt controller that measures the neutral pose first
+    ///
+    /// # Arguments
+    /// * `profile` - How tilting feels
+    pub const fn new(profile: TiltProfile) -> Self {
+        Self {
+            profile,
+            sum: (0, 0, 0),
+            samples: 0,
+            neutral: None,
+            roll: Lean { held: None },
+            pitch: Lean { held: None },
+        }
+    }
+    /// Roll and pitch of the neutral pose
+    ///
+    /// # Returns
+    /// * The angles in degrees, or None while the pose is still being measured
+    pub const fn neutral(&self) -> Option<(f32, f32)> {
+        self.neutral
+    }
+    /// Feed a reading in
+    ///
+    /// # Arguments
+    /// * `now` - When the reading was taken, in milliseconds
+    /// * `reading` - The accelerometer reading
+    /// * `actions` - Where to add the moves
+    pub fn update(&mut self, now: u32, reading: Reading, actions: &mut Actions) {
+        let Some((neutral_roll, neutral_pitch)) = self.neutral else {
+            self.calibrate(reading);
+            return;
+        };
+        let (roll, pitch) = angles(reading);
+        let dead_zone = self.profile.dead_zone_deg;
+        self.roll.update(
+            now,
+            past_dead_zone(roll - neutral_roll, dead_zone),
+            (Action::MoveLeft, Action::MoveRight),
+            &self.profile,
+            actions,
+        );
+        self.pitch.update(
+            now,
+            past_dead_zone(pitch - neutral_pitch, dead_zone),
+            (Action::SoftDrop, Action::Rotate180),
+            &self.profile,
+            actions,
+        );
+    }
+    /// Add a reading to the neutral pose
+    ///
+    /// # Arguments
+    /// * `reading` - The accelerometer reading
+    fn calibrate(&mut self, reading: Reading) {
+        self.sum.0 += reading.0;
+        self.sum.1 += reading.1;
+        self.sum.2 += reading.2;
+        self.samples += 1;
+        if self.samples == Self::CALIBRATION_SAMPLES {
+            let count = self.samples as i32;
+            self.neutral = Some(angles((
+                self.sum.0 / count,
+                self.sum.1 / count,
+                self.sum.2 / count,
+            )));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libm::{cosf, sinf};
+
+    /// Reading of a board at rest, rolled and pitched by some degrees
+    fn pose(roll: f32, pitch: f32) -> Reading {
+        let (roll, pitch) = (roll.to_radians(), pitch.to_radians());
+        (
+            roundf(1000.0 * sinf(roll)) as i32,
+            roundf(1000.0 * sinf(pitch) * cosf(roll)) as i32,
+            roundf(-1000.0 * cosf(pitch) * cosf(roll)) as i32,
+        )
+    }
+
+    /// Feed a trace of readings taken every 10 ms, starting at 0 ms, and
+    /// collect the actions with when they happened
+    fn play(tilt: &mut Tilt, trace: &[Reading]) -> [Option<(u32, Action)>; 16] {
+        let mut moves = [None; 16];
+        let mut count = 0;
+        for (index, &reading) in trace.iter().enumerate() {
+            let now = index as u32 * 10;
+            let mut actions = Actions::new();
+            tilt.update(now, reading, &mut actions);
+            for &action in actions.as_slice() {
+                moves[count] = Some((now, action));
+                count += 1;
+            }
+        }
+        moves
+    }
+
+    /// A tilt controller that has measured a level board
+    fn calibrated() -> Tilt {
+        let mut tilt = Tilt::new(TiltProfile::LED);
+        for _ in 0..Tilt::CALIBRATION_SAMPLES {
+            tilt.update(0, pose(0.0, 0.0), &mut Actions::new());
+        }
+        tilt
+    }
+
+    #[test]
+    fn angles_follow_the_board() {
+        let (roll, pitch) = angles(pose(-20.0, 0.0));
+        assert!((roll + 20.0).abs() < 0.2 && pitch.abs() < 0.2);
+        let (roll, pitch) = angles(pose(0.0, -30.0));
+        assert!(roll.abs() < 0.2 && (pitch + 30.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn steeper_tilts_repeat_faster() {
+        let profile = TiltProfile::LED;
+        assert_eq!(profile.rate_ms(10.0), 150);
+        assert_eq!(profile.rate_ms(20.0), 95);
+        assert_eq!(profile.rate_ms(30.0), 40);
+        assert_eq!(profile.rate_ms(60.0), 40);
+    }
+
+    #[test]
+    fn neutral_pose_is_measured_first() {
+        // Recorded from a board held leaning about 12 degrees right, which
+        // would move the piece if measured from level
+        let resting = [
+            (212, -35, -978),
+            (205, -41, -981),
+            (209, -30, -975),
+            (214, -38, -980),
+        ];
+        let mut tilt = Tilt::new(TiltProfile::LED);
+        let mut trace = [(0, 0, 0); 40];
+        for (index, reading) in trace.iter_mut().enumerate() {
+            *reading = resting[index % resting.len()];
+        }
+        assert_eq!(play(&mut tilt, &trace), [None; 16]);
+        let (roll, pitch) = tilt.neutral().unwrap();
+        assert!((11.0..13.0).contains(&roll) && (-3.0..-1.0).contains(&pitch));
+    }
+
+    #[test]
+    fn wobbles_in_the_dead_zone_do_nothing() {
+        let mut tilt = calibrated();
+        let mut trace = [(0, 0, 0); 50];
+        for (index, reading) in trace.iter_mut().enumerate() {
+            let wobble = if index % 2 == 0 { 9.0 } else { -9.0 };
+            *reading = pose(wobble, -wobble);
+        }
+        assert_eq!(play(&mut tilt, &trace), [None; 16]);
+    }
+
+    #[test]
+    fn lean_holds_until_it_falls_under_release() {
+        let mut tilt = calibrated();
+        // Lean left past engage, ease back between release and engage, then
+        // back to level
+        let mut trace = [(0, 0, 0); 70];
+        for (index, reading) in trace.iter_mut().enumerate() {
+            let roll = match index {
+                0..=4 => 0.0,
+                5..=24 => -16.0,
+                25..=59 => -12.0,
+                _ => -9.0,
+            };
+            *reading = pose(roll, 0.0);
+        }
+        let moves = play(&mut tilt, &trace);
+        assert_eq!(moves[0], Some((50, Action::MoveLeft)));
+        // First repeat after the delay, then 150 ms apart near the engage angle
+        assert_eq!(moves[1], Some((350, Action::MoveLeft)));
+        assert_eq!(moves[2], Some((500, Action::MoveLeft)));
+        assert_eq!(moves[3], None);
+    }
+
+    #[test]
+    fn steep_lean_repeats_faster_than_shallow() {
+        let count = |roll| {
+            let mut tilt = calibrated();
+            let trace = [pose(roll, 0.0); 60];
+            play(&mut tilt, &trace).iter().flatten().count()
+        };
+        assert_eq!(count(16.0), 4);
+        assert_eq!(count(40.0), 9);
+    }
+
+    #[test]
+    fn forward_soft_drops_and_back_flips_once() {
+        let mut tilt = calibrated();
+        let mut trace = [(0, 0, 0); 80];
+        for (index, reading) in trace.iter_mut().enumerate() {
+            let pitch = match index {
+                0..=39 => 25.0,
+                40..=49 => 0.0,
+                _ => -25.0,
+            };
+            *reading = pose(0.0, pitch);
+        }
+        let moves = play(&mut tilt, &trace);
+        // Held back for 400 ms, still only one flip
+        assert_eq!(moves[0], Some((0, Action::Rotate180)));
+        assert_eq!(moves[1], Some((500, Action::SoftDrop)));
+        assert_eq!(moves[2], None);
+    }
+
+    #[test]
+    fn flipping_sides_moves_the_other_way() {
+        let mut tilt = calibrated();
+        let mut trace = [(0, 0, 0); 20];
+        for (index, reading) in trace.iter_mut().enumerate() {
+            let roll = if index < 10 { -20.0 } else { 20.0 };
+            *reading = pose(roll, 0.0);
+        }
+        let moves = play(&mut tilt, &trace);
+        assert_eq!(moves[0], Some((0, Action::MoveLeft)));
+        assert_eq!(moves[1], Some((100, Action::MoveRight)));
+        assert_eq!(moves[2], None);
+    }
+}
@@ -4,41 +4,54 @@
 #![no_std]
 
 mod mylib;
+#[cfg(feature = "accelerometer")]
+use crate::mylib::accel::TiltControl;
+use crate::mylib::beep::beep;
+#[cfg(any(feature = "buttons", feature = "logo"))]
+use crate::mylib::edge_input::{init_edges, rescan};
+use crate::mylib::pixeldisplay::display_frame;
 #[cfg(feature = "text")]
-use crate::mylib::pixeldisplay::scroll_text;
-#[cfg(feature = "text")]
-use crate::mylib::pixeldisplay::{clear_display, display_frame};
+use crate::mylib::pixeldisplay::{clear_display, scroll_text};
 use crate::mylib::{
-    beep::repeat_beep, game::GameState, pixeldisplay::Raster, GameAbstractionLayer,
+    clock::{init_clock, now_ms, sleep_until},
+    GameAbstractionLayer,
 };
 use cortex_m_rt::entry;
-use microbit::hal::prelude::*;
 use microbit::{
-    pac::{interrupt, TIMER0, TIMER2},
+    hal::Timer,
+    pac::{interrupt, TIMER0, TIMER1, TIMER2},
     Board,
 };
 use nanorand::{Pcg64, Rng};
 use panic_rtt_target as _;
+#[cfg(feature = "text")]
+use retris_core::scoring::digits;
+use retris_core::{
+    controller::{Actions, ButtonController, Controller},
+    edges::EdgeQueue,
+    game::{Action, GameState, StepOutcome},
+    render::{draw_preview, render_game, render_shape, LED_PALETTE},
+    schedule::Scheduler,
+    settings::Settings,
+    sound::BeepPattern,
+};
+#[cfg(feature = "debug")]
+use rtt_target::rprintln;
 use rtt_target::rtt_init_print;
 
 microbit_display!(TIMER0);
 microbit_beep!(TIMER2);
 
 /// Main function for the game
+///
+/// Sets up the board and the controllers the enabled features have hardware
+/// for, then plays the game with them.
 #[entry]
 fn main() -> ! {
-    #[cfg(not(feature = "debug"))]
-    play_game();
-    #[cfg(feature = "debug")]
-    demo_inputs();
-}
-
-#[cfg(not(feature = "debug"))]
-fn play_game() -> ! {
     // Setup the serial console for panics
     rtt_init_print!();
-    // Tick time in milliseconds
-    let tick: u16 = 1500;
+    // How long a new button level has to hold before it counts, in milliseconds
+    let debounce: u32 = 10;
     // Take ownership of the Board struct
     let board = Board::take().unwrap();
     // Create our input sources
@@ -47,120 +60,284 @@ fn play_game() -> ! {
     init_beep(gal.speaker_timer, gal.speaker_pin.degrade());
     // Initialize the display
     init_display(gal.display_timer, gal.display_pins);
-    // Setup the random number generator
-    let mut rng = Pcg64::new_seed(1337);
-    let mut seed = rng.generate();
-    // Set up and run a game.
-    let mut game = GameState::new();
-    // Set up screen raster
-    #[cfg(not(feature = "screen"))]
-    let mut raster = Raster::default();
+    // Start the clock the main loop schedules from
+    init_clock(gal.rtc);
     // Show "TETRIS" on the display
     #[cfg(feature = "text")]
     scroll_text("TETRIS", &mut gal.delay);
     // Clear the display before starting the game
     #[cfg(feature = "text")]
     clear_display();
+    // Buttons and logo interrupt on every change instead of being polled
+    #[cfg(any(feature = "buttons", feature = "logo"))]
+    init_edges(gal.gpiote);
+    #[cfg(all(feature = "buttons", feature = "logo"))]
+    let edges = (gal.buttons.sense(), gal.logo.sense());
+    #[cfg(all(feature = "buttons", not(feature = "logo")))]
+    let edges = gal.buttons.sense();
+    #[cfg(all(feature = "logo", not(feature = "buttons")))]
+    let edges = gal.logo.sense();
+    #[cfg(not(any(feature = "buttons", feature = "logo")))]
+    let edges = ();
+    // Holding a button or the logo as the game starts turns the ghost piece off,
+    // since inputs that are already pressed queue their press as sensing starts
+    let settings = Settings {
+        ghost: !edges.peek().is_some_and(|edge| edge.pressed),
+        ..Settings::default()
+    };
+    let buttons = ButtonController::new(edges, debounce, settings.auto_repeat);
+    // Tilting the board moves and soft drops the piece alongside the buttons and logo,
+    // measured from the pose the board is held in as the game starts
+    #[cfg(feature = "accelerometer")]
+    let controller = (buttons, TiltControl::new(gal.accel, settings.tilt));
+    #[cfg(not(feature = "accelerometer"))]
+    let controller = buttons;
+    // Print every action to the serial console
+    #[cfg(feature = "debug")]
+    let controller = Logged(controller);
+    play_game(controller, settings, &mut gal.delay)
+}
+
+/// Things the main loop does when their deadline comes up
+#[derive(Clone, Copy)]
+enum Event {
+    /// Ask the controller for actions
+    Input,
+    /// Let the falling piece fall a row
+    Gravity,
+    /// Lock the resting piece once its lock delay runs out
+    Lock,
+    /// Stop blinking the preview of the next piece
+    Preview,
+    /// Stop showing the piece that was put in the hold slot
+    Flash,
+    /// Sound one beep of a pattern, with the beeps left and the gap between them
+    Beep(u8, u16),
+}
+
+/// Play a game until it is over
+///
+/// # Arguments
+/// * `controller` - Where the player's actions come from
+/// * `settings` - The player settings
+/// * `delay` - The board timer for scrolling text
+fn play_game<C: Controller>(mut controller: C, settings: Settings, delay: &mut Timer<TIMER1>) -> ! {
+    // How long the held piece is shown after a hold, in milliseconds
+    let hold_flash: u32 = 300;
+    // How long the next piece preview blinks at the start of each tick, in milliseconds
+    let preview_blink: u16 = 200;
+    // How often the controller is asked for actions, in milliseconds
+    let input_period: u32 = 10;
+    // Setup the random number generator
+    let mut rng = Pcg64::new_seed(1337);
+    let mut seed = rng.generate();
+    // Set up and run a game on the 5x5 pixel display.
+    let mut game: GameState<5, 5> = GameState::new();
+    let mut schedule: Scheduler<Event, 6> = Scheduler::new();
+    let start = now_ms();
+    schedule.schedule(start, Event::Input);
+    schedule.schedule(start, Event::Gravity);
+    // Time the lock delay has been told about
+    let mut advanced = start;
+    // What is drawn over the game until its own event ends it
+    let (mut previewing, mut flashing) = (false, false);
     // Loop to play game
     loop {
-        gal.delay.delay_ms(tick);
-        if matches!(gal.buttons.read_a(), Some(true)) {
-            game.move_left(&mut raster);
+        let now = now_ms();
+        let Some(event) = schedule.pop_due(now) else {
+            // Sleep until the next deadline, or until an interrupt comes first
+            if let Some(at) = schedule.next_deadline(now) {
+                sleep_until(at);
+            }
+            continue;
+        };
+        // Let the lock delay see the time that passed, so moves made while the
+        // piece rests reset it from the time they happen
+        game.advance(now.wrapping_sub(advanced));
+        advanced = now;
+        let mut outcome = StepOutcome::default();
+        let mut moved = false;
+        match event {
+            Event::Input => {
+                schedule.schedule_in(now, input_period, Event::Input);
+                // Catch the edges of pins the interrupt stopped hearing from
+                #[cfg(any(feature = "buttons", feature = "logo"))]
+                rescan();
+                let mut actions = Actions::new();
+                controller.poll(now, &mut actions);
+                moved = !actions.is_empty();
+                for &action in actions.as_slice() {
+                    let result = game.apply(action);
+                    if result.soft_drop > 0 {
+                        // A soft drop restarts the gravity timer
+                        let tick = game.gravity_interval();
+                        schedule.schedule_in(now, tick.into(), Event::Gravity);
+                    }
+                    if let (Action::Hold, true, Some(kind)) =
+                        (action, result.piece_held, game.held_piece())
+                    {
+                        // Briefly show the piece that was put away
+                        let shape = game.rules().piece_set.shape(kind);
+                        display_frame(&render_shape(shape, &LED_PALETTE));
+                        flashing = true;
+                        schedule.schedule_in(now, hold_flash, Event::Flash);
+                    }
+                    outcome = outcome.then(result);
+                }
+            }
+            Event::Gravity => {
+                outcome = game.step(seed);
+                seed = rng.generate();
+                // Tick time in milliseconds, shortening as the level goes up
+                let tick = game.gravity_interval();
+                schedule.schedule_in(now, tick.into(), Event::Gravity);
+                // Blink a dim preview of the next piece, shorter than fast ticks
+                previewing = !game.next_pieces().is_empty();
+                if previewing {
+                    schedule.schedule_in(now, preview_blink.min(tick / 2).into(), Event::Preview);
+                }
+                moved = true;
+            }
+            Event::Lock => {
+                outcome = game.lock_if_due();
+                moved = outcome.piece_locked;
+            }
+            Event::Preview => {
+                previewing = false;
+                moved = true;
+            }
+            Event::Flash => {
+                flashing = false;
+                moved = true;
+            }
+            Event::Beep(left, gap) => {
+                beep();
+                if left > 1 {
+                    schedule.schedule_in(now, gap.into(), Event::Beep(left - 1, gap));
+                }
+            }
         }
-        if matches!(gal.buttons.read_b(), Some(true)) {
-            game.move_right(&mut raster);
+        // Spawn the next piece right away, whichever event locked the last one
+        if outcome.piece_locked {
+            schedule.schedule(now, Event::Gravity);
         }
-        if matches!(gal.logo.read_logo(), Some(true)) {
-            game.rotate_piece(&mut raster);
+        // Lock the resting piece on time rather than on the next gravity step
+        if let Some(left) = game.lock_remaining() {
+            schedule.schedule_in(now, left, Event::Lock);
+        } else {
+            schedule.cancel(Event::Lock);
         }
-        let clr_rows = game.step(&mut raster, seed);
-        seed = rng.generate();
-        if clr_rows > 0 && clr_rows != 7 {
-            // Beep for each row cleared
-            repeat_beep(clr_rows, 75u16, &mut gal.delay);
-        } else if clr_rows == 7 {
-            // Game over
-            loop {
-                #[cfg(feature = "text")]
-                clear_display();
-                #[cfg(feature = "text")]
-                scroll_text("GAME OVER", &mut gal.delay);
+        #[cfg(feature = "debug")]
+        log_outcome(&game, &outcome);
+        if outcome.game_over.is_some() {
+            game_over(game.score(), delay);
+        }
+        if let Some(pattern) = BeepPattern::for_outcome(&outcome) {
+            schedule.schedule(now, Event::Beep(pattern.beeps, pattern.gap_ms));
+        }
+        if moved && !flashing {
+            let mut frame = render_game(&game, &settings, &LED_PALETTE);
+            if let (true, Some(&kind)) = (previewing, game.next_pieces().first()) {
+                let piece_set = game.rules().piece_set;
+                let shape = piece_set.shape(kind);
+                let col = piece_set.spawn_col(5, shape);
+                draw_preview(&mut frame, game.board(), shape, col, &LED_PALETTE);
             }
+            display_frame(&frame);
         }
-        display_frame(&raster);
     }
 }
 
-#[cfg(feature = "debug")]
-fn demo_inputs() -> ! {
-    // Setup the serial console
-    use rtt_target::rprintln;
-    // Import beep function
-    use crate::mylib::beep::beep;
-    rtt_init_print!();
-    // Tick time in milliseconds
-    let tick: u16 = 1500;
-    // Take ownership of the Board struct
-    let board = Board::take().unwrap();
-    // Create our input sources
-    let mut gal = GameAbstractionLayer::new(board);
-    // Initialize the speaker
-    init_beep(gal.speaker_timer, gal.speaker_pin.degrade());
-    // Initialize the display
-    init_display(gal.display_timer, gal.display_pins);
-    // Beep to indicate start of demo
-    beep();
-    // Setup the random number generator
-    let mut rng = Pcg64::new_seed(0);
-    let mut seed = rng.generate();
-    // Set up and run a game.
-    let mut game = GameState::new();
-    // Set up screen raster
-    #[cfg(not(feature = "screen"))]
-    let mut raster = Raster::default();
-    // Loop and read input data and print to serial console via probe-rs and rtt
+/// Scroll the final score forever
+///
+/// # Arguments
+/// * `score` - The final score
+/// * `delay` - The board timer for scrolling text
+#[cfg(feature = "text")]
+fn game_over(score: u32, delay: &mut Timer<TIMER1>) -> ! {
+    let mut buf = [0; 10];
+    let score = digits(score, &mut buf);
     loop {
-        gal.delay.delay_ms(tick);
-        let data = gal.accel.read_accel().unwrap();
-        rprintln!("x {} y {} z {}", data.0, data.1, data.2);
-        if matches!(gal.buttons.read_a(), Some(true)) {
-            rprintln!("button a pressed");
-            game.move_left(&mut raster);
-            repeat_beep(1u8, 75u16, &mut gal.delay)
-        }
-        if matches!(gal.buttons.read_b(), Some(true)) {
-            rprintln!("button b pressed");
-            game.move_right(&mut raster);
-            repeat_beep(2u8, 75u16, &mut gal.delay)
-        }
-        if matches!(gal.logo.read_logo(), Some(true)) {
-            rprintln!("logo pressed");
-            game.rotate_piece(&mut raster);
-            repeat_beep(3u8, 75u16, &mut gal.delay)
+        clear_display();
+        scroll_text("GAME OVER", delay);
+        scroll_text(score, delay);
+    }
+}
+
+/// Leave the final board on the display forever, since there is no text to scroll
+///
+/// # Arguments
+/// * `score` - The final score
+/// * `delay` - The board timer for scrolling text
+#[cfg(not(feature = "text"))]
+fn game_over(_score: u32, _delay: &mut Timer<TIMER1>) -> ! {
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+/// Controller that prints the actions of another to the serial console
+#[cfg(feature = "debug")]
+struct Logged<C>(C);
+
+#[cfg(feature = "debug")]
+impl<C: Controller> Controller for Logged<C> {
+    fn poll(&mut self, now: u32, actions: &mut Actions) {
+        let before = actions.as_slice().len();
+        self.0.poll(now, actions);
+        for action in &actions.as_slice()[before..] {
+            rprintln!("{} ms: {:?}", now, action);
         }
-        if gal.accel.tilt_left() {
-            rprintln!("tilted left");
-            repeat_beep(1u8, 75u16, &mut gal.delay);
-            gal.delay.delay_ms(100_u32);
+    }
+}
+
+/// Print what happened in a step to the serial console
+///
+/// # Arguments
+/// * `game` - The game after the step
+/// * `outcome` - What happened in the step
+#[cfg(feature = "debug")]
+fn log_outcome(game: &GameState<5, 5>, outcome: &StepOutcome) {
+    match *outcome {
+        StepOutcome {
+            game_over: Some(reason),
+            ..
+        } => {
+            rprintln!("game over: {:?}", reason);
+            rprintln!(
+                "score {} lines {} level {}",
+                game.score(),
+                game.lines(),
+                game.level()
+            );
         }
-        if gal.accel.tilt_right() {
-            rprintln!("tilted right");
-            repeat_beep(2u8, 75u16, &mut gal.delay);
-            gal.delay.delay_ms(100_u32);
+        StepOutcome {
+            t_spin: Some(t_spin),
+            lines_cleared,
+            points,
+            ..
+        } => {
+            rprintln!(
+                "{:?} t-spin with {} rows for {} points",
+                t_spin,
+                lines_cleared,
+                points
+            );
         }
-        let clr_rows = game.step(&mut raster, seed);
-        seed = rng.generate();
-        if clr_rows > 0 && clr_rows != 7 {
-            repeat_beep(clr_rows, 75u16, &mut gal.delay);
-        } else if clr_rows == 7 {
-            loop {
-                #[cfg(feature = "text")]
-                clear_display();
-                #[cfg(feature = "text")]
-                scroll_text("GAME OVER", &mut gal.delay);
+        StepOutcome {
+            lines_cleared,
+            points,
+            all_clear,
+            combo,
+            back_to_back,
+            ..
+        } if lines_cleared > 0 => {
+            rprintln!("cleared {} rows for {} points", lines_cleared, points);
+            rprintln!("combo {:?} back-to-back {:?}", combo, back_to_back);
+            if all_clear {
+                rprintln!("all clear");
             }
         }
-        display_frame(&raster);
-        rprintln!("row: {} col: {}", game.fall_loc.row, game.fall_loc.col);
+        _ => {}
     }
 }
@@ -8,6 +8,10 @@ use lsm303agr::{
     interface::I2cInterface, mode::MagOneShot, AccelMode, AccelOutputDataRate, Lsm303agr,
 };
 use microbit::{hal::twim, pac::TWIM0};
+use retris_core::{
+    controller::{Actions, Controller},
+    tilt::{Tilt, TiltProfile},
+};
 use rtt_target::rprintln;
 
 /// Custom typedef for the accelerometer to shorten the typename
@@ -50,34 +54,38 @@ impl Accel {
         }
         None
     }
+}
 
-    /// Tilted left sensor
+/// The accelerometer as a controller, moving pieces by tilting the board
+pub struct TiltControl {
+    /// Accelerometer sensor
+    accel: Accel,
+    /// Turns the readings into moves
+    tilt: Tilt,
+}
+
+impl TiltControl {
+    /// Set up tilt controls, which measure the neutral pose over the first readings
+    ///
+    /// # Arguments
+    /// * `accel` - The accelerometer
+    /// * `profile` - How tilting the board feels
     ///
     /// # Returns
-    /// * `bool` - If the sensor is tilted left
-    pub fn tilt_left(&mut self) -> bool {
-        let data = self.accel.accel_data();
-        if let Ok(data) = data {
-            // Account for some noise in the input which when perfectly still can vary
-            if data.x < -400 {
-                return true;
-            }
+    /// * `Self` - The tilt controls as a struct
+    pub fn new(accel: Accel, profile: TiltProfile) -> Self {
+        Self {
+            accel,
+            tilt: Tilt::new(profile),
         }
-        false
     }
+}
 
-    /// Tilted right sensor
-    ///
-    /// # Returns
-    /// * `bool` - If the sensor is tilted right
-    pub fn tilt_right(&mut self) -> bool {
-        let data = self.accel.accel_data();
-        if let Ok(data) = data {
-            // Account for some noise in the input which when perfectly still can vary
-            if data.x > 400 {
-                return true;
-            }
+impl Controller for TiltControl {
+    fn poll(&mut self, now: u32, actions: &mut Actions) {
+        // A failed read skips the tick, the next one catches up
+        if let Some(reading) = self.accel.read_accel() {
+            self.tilt.update(now, reading, actions);
         }
-        false
     }
 }
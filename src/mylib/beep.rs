@@ -5,10 +5,7 @@
 //! Taken from my `breakout` project for the Embedded Rust class
 //!
 //! Original code by Bart Massey with modifications by Gatlin Newhouse
-use microbit::{
-    hal::{prelude::*, Timer},
-    pac::TIMER1,
-};
+use microbit::hal::prelude::*;
 
 use crate::BEEP;
 
@@ -132,16 +129,3 @@ pub fn beep() {
         }
     });
 }
-
-/// Beep "beeps" times with a delay between beeps.
-///
-/// # Arguments
-/// * `beeps` - The number of beeps to make in u8
-/// * `delay` - The delay between beeps in milliseconds as u16
-/// * `board_timer` - The board timer to use for the delay of beeps
-pub fn repeat_beep(beeps: u8, delay: u16, board_timer: &mut Timer<TIMER1>) {
-    for _ in 0..beeps {
-        beep();
-        board_timer.delay_ms(delay);
-    }
-}
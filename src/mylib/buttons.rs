@@ -1,17 +1,20 @@
 //! Use the A and B buttons as controls for the game
 //!
 //! Taken from my `breakout` project for the Embedded Rust class
-use microbit::hal::{
-    gpio::{p0::P0_14, p0::P0_23, Floating, Input},
-    prelude::InputPin,
+use microbit::hal::gpio::{p0::P0_14, p0::P0_23, Floating, Input};
+use retris_core::{
+    edges::{Edge, EdgeQueue},
+    input,
 };
 
+use crate::mylib::edge_input::{sense, SensedEdges};
+
 /// Material needed for button presses
 pub struct Buttons {
     /// A button
-    pub a: P0_14<Input<Floating>>,
+    a: P0_14<Input<Floating>>,
     /// B button
-    pub b: P0_23<Input<Floating>>,
+    b: P0_23<Input<Floating>>,
 }
 
 impl Buttons {
@@ -29,27 +32,26 @@ impl Buttons {
         Self { a, b }
     }
 
-    /// Check if the A button is pressed
+    /// Start sensing the buttons, once the input driver is set up
     ///
     /// # Returns
-    /// * `Option<bool>` - Whether the A button is pressed, Some(true) if pressed, None if not pressed
-    pub fn read_a(&self) -> Option<bool> {
-        if self.a.is_low().unwrap() {
-            Some(true)
-        } else {
-            None
-        }
+    /// * `ButtonEdges` - The edges of both buttons
+    pub fn sense(self) -> ButtonEdges {
+        ButtonEdges((
+            sense(input::Input::A, self.a.degrade()),
+            sense(input::Input::B, self.b.degrade()),
+        ))
     }
+}
 
-    /// Check if the B button is pressed
-    ///
-    /// # Returns
-    /// * `Option<bool>` - Whether the B button is pressed, Some(true) if pressed, None if not pressed
-    pub fn read_b(&self) -> Option<bool> {
-        if self.b.is_low().unwrap() {
-            Some(true)
-        } else {
-            None
-        }
+/// Edges of the A and B buttons as the interrupt senses them, in time order
+pub struct ButtonEdges((SensedEdges, SensedEdges));
+
+impl EdgeQueue for ButtonEdges {
+    fn peek(&self) -> Option<Edge> {
+        self.0.peek()
+    }
+    fn pop(&mut self) -> Option<Edge> {
+        self.0.pop()
     }
 }
@@ -0,0 +1,114 @@
+//! Free-running millisecond clock for the main loop
+//!
+//! RTC0 counts the 32.768 kHz low frequency clock in 24 bits, which rolls
+//! over about every eight and a half minutes, so its overflow interrupt counts
+//! the rollovers to extend it. A compare register wakes the core when the
+//! main loop sleeps until its next deadline.
+use core::cell::RefCell;
+use cortex_m::interrupt::Mutex;
+use microbit::{
+    hal::rtc::{Rtc, RtcCompareReg, RtcInterrupt},
+    pac::{interrupt, RTC0},
+};
+use retris_core::schedule::{millis, ticks};
+
+/// Ticks per second of the RTC without a prescaler
+const RTC_HZ: u32 = 32_768;
+
+/// Mask of the 24 bits the RTC counts in
+const COUNTER_MASK: u32 = 0xFF_FFFF;
+
+/// Global state of the clock
+static CLOCK: Mutex<RefCell<Option<Clock>>> = Mutex::new(RefCell::new(None));
+
+/// Clock status elements
+struct Clock {
+    /// Counter of low frequency clock ticks
+    rtc: Rtc<RTC0>,
+    /// Number of times the counter has rolled over
+    overflows: u32,
+}
+
+/// Handle the counter rolling over or reaching a wake-up time
+#[interrupt]
+fn RTC0() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(clock) = CLOCK.borrow(cs).borrow_mut().as_mut() {
+            if clock.rtc.is_event_triggered(RtcInterrupt::Overflow) {
+                clock.rtc.reset_event(RtcInterrupt::Overflow);
+                clock.overflows = clock.overflows.wrapping_add(1);
+            }
+            // Waking the core was all the compare was for
+            clock.rtc.reset_event(RtcInterrupt::Compare0);
+        }
+    });
+}
+
+/// Set up and start the clock
+///
+/// The low frequency clock must already be running.
+///
+/// # Arguments
+/// * `rtc` - The RTC peripheral to count with, RTC0
+pub fn init_clock(rtc: RTC0) {
+    cortex_m::interrupt::free(|cs| {
+        let mut rtc = Rtc::new(rtc, 0).unwrap();
+        rtc.enable_interrupt(RtcInterrupt::Overflow, None);
+        rtc.enable_interrupt(RtcInterrupt::Compare0, None);
+        rtc.enable_counter();
+        *CLOCK.borrow(cs).borrow_mut() = Some(Clock { rtc, overflows: 0 });
+
+        unsafe {
+            microbit::pac::NVIC::unmask(microbit::pac::Interrupt::RTC0);
+        }
+    });
+}
+
+/// Time since the clock started
+///
+/// # Returns
+/// * `u32` - Milliseconds since `init_clock`, wrapping around after about 49 days
+pub fn now_ms() -> u32 {
+    cortex_m::interrupt::free(|cs| {
+        let clock = CLOCK.borrow(cs).borrow();
+        let Some(clock) = clock.as_ref() else {
+            return 0;
+        };
+        let mut overflows = clock.overflows;
+        let mut counter = clock.rtc.get_counter();
+        // A rollover while interrupts are off has not been counted yet
+        if clock.rtc.is_event_triggered(RtcInterrupt::Overflow) {
+            overflows = overflows.wrapping_add(1);
+            counter = clock.rtc.get_counter();
+        }
+        millis(((overflows as u64) << 24) | counter as u64, RTC_HZ)
+    })
+}
+
+/// Sleep until a point in time, or until an interrupt wakes the core first
+///
+/// The compare is armed and the core sleeps with interrupts masked, so an
+/// interrupt that comes in between still wakes it, and is handled after.
+///
+/// # Arguments
+/// * `at` - When to wake up, in milliseconds since `init_clock`
+pub fn sleep_until(at: u32) {
+    cortex_m::interrupt::free(|cs| {
+        let wait = at.wrapping_sub(now_ms()) as i32;
+        if wait <= 0 {
+            return;
+        }
+        if let Some(clock) = CLOCK.borrow(cs).borrow_mut().as_mut() {
+            // The counter does not see a compare one tick ahead, and it cannot
+            // wait longer than one rollover
+            let wait = ticks(wait as u32, RTC_HZ).clamp(2, COUNTER_MASK as u64) as u32;
+            let compare = clock.rtc.get_counter().wrapping_add(wait) & COUNTER_MASK;
+            clock.rtc.reset_event(RtcInterrupt::Compare0);
+            clock
+                .rtc
+                .set_compare(RtcCompareReg::Compare0, compare)
+                .unwrap();
+        }
+        cortex_m::asm::wfi();
+    });
+}
@@ -0,0 +1,166 @@
+//! Interrupt-driven buttons and logo
+//!
+//! GPIOTE raises its PORT event on the rising edge of DETECT, which is high
+//! while any sensed pin is at the level it is sensing for. Each pin senses for
+//! the opposite of its last queued level, so the interrupt fires on a change,
+//! stamps it with the clock, and pushes an edge into the lock-free queue of
+//! that input.
+//!
+//! A pin whose edge could not be queued, or that changed again while the
+//! interrupt was scanning, keeps DETECT high, and then no pin raises another
+//! PORT event. The game loop calls [`rescan`] every input tick to queue those
+//! edges late rather than lose every input from then on. The buttons and logo hand
+//! their queues to the game loop as an `EdgeQueue`, and the game loop never
+//! reads the pins itself.
+use core::cell::RefCell;
+use cortex_m::interrupt::Mutex;
+use microbit::{
+    hal::{
+        gpio::{Floating, Input, Pin},
+        gpiote::Gpiote,
+        prelude::InputPin,
+    },
+    pac::{interrupt, GPIOTE},
+};
+use retris_core::{
+    edges::{Edge, EdgeConsumer, EdgeProducer, EdgeRing},
+    input,
+};
+
+use crate::mylib::clock::now_ms;
+
+/// Most edges of one input that can wait for the game loop
+pub const QUEUE_LEN: usize = 16;
+
+/// Edges from the interrupt to the game loop, one queue for each input in the
+/// order of `input::Input::ALL`
+static EDGES: [EdgeRing<QUEUE_LEN>; 3] = [EdgeRing::new(), EdgeRing::new(), EdgeRing::new()];
+
+/// Edges of one input as the interrupt senses them
+pub type SensedEdges = EdgeConsumer<'static, QUEUE_LEN>;
+
+/// Global state of the input driver
+static DRIVER: Mutex<RefCell<Option<EdgeDriver>>> = Mutex::new(RefCell::new(None));
+
+/// One input being sensed
+struct Sensed {
+    /// Pin of the input
+    pin: Pin<Input<Floating>>,
+    /// Last level queued, true if pressed
+    level: bool,
+    /// End of the input's queue the interrupt pushes to
+    producer: EdgeProducer<'static, QUEUE_LEN>,
+}
+
+/// Input driver status elements
+struct EdgeDriver {
+    /// GPIOTE peripheral sensing the pins
+    gpiote: Gpiote,
+    /// Each input in the order of `input::Input::ALL`, if it is sensed
+    inputs: [Option<Sensed>; 3],
+}
+
+impl EdgeDriver {
+    /// Push an edge for each pin that changed and sense for its next change
+    fn scan(&mut self) {
+        let now = now_ms();
+        let port = self.gpiote.port();
+        for (sensed, input) in self.inputs.iter_mut().zip(input::Input::ALL) {
+            let Some(sensed) = sensed else {
+                continue;
+            };
+            // The buttons and logo pull their pins low when pressed
+            let pressed = sensed.pin.is_low().unwrap();
+            // Only record the level once its edge is queued, so an edge dropped
+            // by a full queue is tried again on the next rescan
+            if pressed != sensed.level
+                && sensed.producer.push(Edge {
+                    at: now,
+                    input,
+                    pressed,
+                })
+            {
+                sensed.level = pressed;
+            }
+            // Sense for the opposite of the recorded level. A pin left matching
+            // it holds DETECT high until a rescan queues its edge
+            let sense = port.input_pin(&sensed.pin);
+            if sensed.level {
+                sense.high();
+            } else {
+                sense.low();
+            }
+        }
+    }
+}
+
+/// Handle a pin change
+#[interrupt]
+fn GPIOTE() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(driver) = DRIVER.borrow(cs).borrow_mut().as_mut() {
+            driver.gpiote.port().reset_events();
+            driver.scan();
+        }
+    });
+}
+
+/// Queue the edges the PORT event missed and sense every pin again
+///
+/// Edges found here are stamped with the time of the rescan rather than of
+/// the change, at most an input tick late.
+pub fn rescan() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(driver) = DRIVER.borrow(cs).borrow_mut().as_mut() {
+            driver.scan();
+        }
+    });
+}
+
+/// Set up the input driver, which senses no inputs until [`sense`] adds them
+///
+/// The clock must already be running to stamp the edges.
+///
+/// # Arguments
+/// * `gpiote` - The GPIOTE peripheral
+pub fn init_edges(gpiote: GPIOTE) {
+    cortex_m::interrupt::free(|cs| {
+        let driver = EdgeDriver {
+            gpiote: Gpiote::new(gpiote),
+            inputs: [None, None, None],
+        };
+        driver.gpiote.port().reset_events();
+        driver.gpiote.port().enable_interrupt();
+        *DRIVER.borrow(cs).borrow_mut() = Some(driver);
+
+        unsafe {
+            microbit::pac::NVIC::unmask(microbit::pac::Interrupt::GPIOTE);
+        }
+    });
+}
+
+/// Start sensing an input
+///
+/// The input driver must be set up, and each input can only be sensed once.
+/// An input that is already pressed gets an edge right away.
+///
+/// # Arguments
+/// * `input` - The input the pin belongs to
+/// * `pin` - The pin of the input
+///
+/// # Returns
+/// * `SensedEdges` - The end of the input's queue for the game loop to drain
+pub fn sense(input: input::Input, pin: Pin<Input<Floating>>) -> SensedEdges {
+    let (producer, consumer) = EDGES[input as usize].split().unwrap();
+    cortex_m::interrupt::free(|cs| {
+        let mut driver = DRIVER.borrow(cs).borrow_mut();
+        let driver = driver.as_mut().unwrap();
+        driver.inputs[input as usize] = Some(Sensed {
+            pin,
+            level: false,
+            producer,
+        });
+        driver.scan();
+    });
+    consumer
+}
@@ -6,15 +6,18 @@
 //! <https://github.com/pdx-cs-embedded-rust/mb2-touch>
 //!
 //! Thanks Bart!
-use microbit::hal::{
-    gpio::{p1::P1_04, Floating, Input},
-    prelude::InputPin,
+use microbit::hal::gpio::{p1::P1_04, Floating, Input};
+use retris_core::{
+    edges::{Edge, EdgeQueue},
+    input,
 };
 
+use crate::mylib::edge_input::{sense, SensedEdges};
+
 /// Material needed for button presses
 pub struct LogoButton {
     /// Logo button
-    pub logo: P1_04<Input<Floating>>,
+    logo: P1_04<Input<Floating>>,
 }
 
 impl LogoButton {
@@ -29,15 +32,23 @@ impl LogoButton {
         Self { logo }
     }
 
-    /// Check if the logo button is pressed
+    /// Start sensing the logo, once the input driver is set up
     ///
     /// # Returns
-    /// * `Option<bool>` - Whether the logo button is pressed, Some(true) if pressed, None if not pressed
-    pub fn read_logo(&self) -> Option<bool> {
-        if self.logo.is_low().unwrap() {
-            Some(true)
-        } else {
-            None
-        }
+    /// * `LogoEdges` - The edges of the logo
+    pub fn sense(self) -> LogoEdges {
+        LogoEdges(sense(input::Input::Logo, self.logo.degrade()))
+    }
+}
+
+/// Edges of the logo as the interrupt senses them
+pub struct LogoEdges(SensedEdges);
+
+impl EdgeQueue for LogoEdges {
+    fn peek(&self) -> Option<Edge> {
+        self.0.peek()
+    }
+    fn pop(&mut self) -> Option<Edge> {
+        self.0.pop()
     }
 }
@@ -3,25 +3,31 @@
 //! Wraps inputs for the game based on cargo features enabled
 //!
 //! Acts as an abstraction layer for what we want to do with the board
+#[cfg(any(feature = "buttons", feature = "logo"))]
+use microbit::pac::GPIOTE;
 #[cfg(feature = "accelerometer")]
 use microbit::{hal::twim, pac::twim0::frequency::FREQUENCY_A};
 use microbit::{
     hal::{
+        clocks::Clocks,
         gpio::{p0::P0_00, Disconnected},
         Timer,
     },
-    pac::{TIMER0, TIMER1, TIMER2},
+    pac::{RTC0, TIMER0, TIMER1, TIMER2},
     Board,
 };
 
 /// Module for the built-in speaker on the micro:bit v2
 pub mod beep;
-/// Module for the game logic
-pub mod game;
-mod rand;
+/// Module for the free-running millisecond clock
+pub mod clock;
+/// Module for the interrupt-driven buttons and logo
+#[cfg(any(feature = "buttons", feature = "logo"))]
+pub mod edge_input;
 
+/// Module for the accelerometer tilt controls
 #[cfg(feature = "accelerometer")]
-mod accel;
+pub mod accel;
 #[cfg(feature = "buttons")]
 mod buttons;
 /// Module for displaying text on the built-in 5x5 pixeldisplay on the micro:bit v2
@@ -31,13 +37,11 @@ pub mod font;
 mod logo;
 #[cfg(feature = "text")]
 mod pendolino;
-/// Module for the built-in 5x5 pixeldisplay on the micro:bit v2
-#[cfg(not(feature = "screen"))]
+/// Module for the built-in 5x5 pixeldisplay on the micro:bit v2, which the
+/// screen builds also draw on until the screen has a driver
 pub mod pixeldisplay;
 #[cfg(feature = "screen")]
 mod screen;
-#[cfg(not(feature = "screen"))]
-mod tetrominos;
 
 /// Inputs for the game as a struct with feature compilation
 pub struct GameAbstractionLayer {
@@ -53,7 +57,6 @@ pub struct GameAbstractionLayer {
     /// Display timer
     pub display_timer: TIMER0,
     /// Display pins
-    #[cfg(not(feature = "screen"))]
     pub display_pins: microbit::gpio::DisplayPins,
     /// Board timer1 as delay
     pub delay: Timer<TIMER1>,
@@ -61,6 +64,11 @@ pub struct GameAbstractionLayer {
     pub speaker_timer: TIMER2,
     /// Speaker pin
     pub speaker_pin: P0_00<Disconnected>,
+    /// Real time counter for the free-running clock
+    pub rtc: RTC0,
+    /// GPIO tasks and events for the interrupt-driven inputs
+    #[cfg(any(feature = "buttons", feature = "logo"))]
+    pub gpiote: GPIOTE,
 }
 
 impl GameAbstractionLayer {
@@ -72,6 +80,8 @@ impl GameAbstractionLayer {
     /// # Returns
     /// * `Self` - The feature enabled inputs as a struct
     pub fn new(board: Board) -> Self {
+        // The real time counter runs from the low frequency clock
+        Clocks::new(board.CLOCK).start_lfclk();
         Self {
             #[cfg(feature = "buttons")]
             buttons: buttons::Buttons::new(board.buttons.button_a, board.buttons.button_b),
@@ -84,11 +94,13 @@ impl GameAbstractionLayer {
                 accel::Accel::new(i2c)
             },
             display_timer: board.TIMER0,
-            #[cfg(not(feature = "screen"))]
             display_pins: board.display_pins,
             delay: Timer::new(board.TIMER1),
             speaker_timer: board.TIMER2,
             speaker_pin: board.speaker_pin,
+            rtc: board.RTC0,
+            #[cfg(any(feature = "buttons", feature = "logo"))]
+            gpiote: board.GPIOTE,
         }
     }
 }
@@ -12,9 +12,7 @@ use microbit::display::nonblocking::GreyscaleImage;
 use microbit::hal::Timer;
 #[cfg(feature = "text")]
 use microbit::pac::TIMER1;
-
-/// LED array proxy for rendering. Indexed as row and then column.
-pub type Raster = [[u8; 5]; 5];
+pub use retris_core::Raster;
 
 /// Wrapper around a Raster to make it easier to use.
 #[cfg(feature = "text")]
@@ -137,8 +135,8 @@ pub fn scroll_text(s: &str, board_timer: &mut Timer<TIMER1>) {
                     }
                 }
             }
-            for j in 0..5 {
-                frame[j][4] = char_frame[j][i];
+            for (row, char_row) in frame.iter_mut().zip(char_frame) {
+                row[4] = char_row[i];
             }
             display_frame(&frame);
             board_timer.delay_ms(100u16);